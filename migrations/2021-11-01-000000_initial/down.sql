@@ -0,0 +1,6 @@
+DROP TABLE Settings;
+DROP TABLE Roles;
+DROP TABLE Tokens;
+DROP TABLE Participants;
+DROP TABLE Years;
+DROP TABLE Users;
@@ -0,0 +1,61 @@
+-- Databases set up before there were migrations already have these tables.
+
+CREATE TABLE IF NOT EXISTS Users (
+	cid TEXT NOT NULL,
+	aoc_id TEXT NOT NULL,
+
+	UNIQUE (aoc_id),
+
+	PRIMARY KEY (cid)
+);
+
+CREATE TABLE IF NOT EXISTS Years (
+	year INTEGER NOT NULL,
+	leaderboard TEXT NOT NULL,
+
+	PRIMARY KEY (year)
+);
+
+CREATE TABLE IF NOT EXISTS Participants (
+	cid TEXT NOT NULL,
+	year INTEGER NOT NULL,
+	github TEXT,
+
+	UNIQUE (cid, year),
+
+	FOREIGN KEY (cid) REFERENCES Users(cid) ON DELETE CASCADE,
+	FOREIGN KEY (year) REFERENCES Years(year) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS Tokens (
+	id SERIAL NOT NULL,
+	cid TEXT NOT NULL,
+	name TEXT NOT NULL,
+	token_hash TEXT NOT NULL,
+
+	UNIQUE (cid, name),
+	UNIQUE (token_hash),
+
+	PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS Roles (
+	id SERIAL NOT NULL,
+	subject TEXT NOT NULL,
+	is_group BOOLEAN NOT NULL,
+	role TEXT NOT NULL,
+	year INTEGER,
+
+	UNIQUE (subject, is_group, role, year),
+
+	FOREIGN KEY (year) REFERENCES Years(year) ON DELETE CASCADE,
+
+	PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS Settings (
+	key TEXT NOT NULL,
+	value TEXT NOT NULL,
+
+	PRIMARY KEY (key)
+);
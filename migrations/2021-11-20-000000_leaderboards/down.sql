@@ -0,0 +1,15 @@
+-- Only the first leaderboard of each year is kept.
+
+ALTER TABLE Years ADD COLUMN leaderboard TEXT NOT NULL DEFAULT '';
+
+UPDATE Years SET leaderboard = COALESCE((
+	SELECT id || '-' || secret
+	FROM Leaderboards
+	WHERE Leaderboards.year = Years.year
+	ORDER BY id
+	LIMIT 1
+), '');
+
+ALTER TABLE Years ALTER COLUMN leaderboard DROP DEFAULT;
+
+DROP TABLE Leaderboards;
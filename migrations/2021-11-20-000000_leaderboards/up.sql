@@ -0,0 +1,17 @@
+-- A year may use several private leaderboards, AoC caps them at 200 members.
+
+CREATE TABLE Leaderboards (
+	year INTEGER NOT NULL,
+	id TEXT NOT NULL,
+	secret TEXT NOT NULL,
+
+	FOREIGN KEY (year) REFERENCES Years(year) ON DELETE CASCADE,
+
+	PRIMARY KEY (year, id)
+);
+
+INSERT INTO Leaderboards (year, id, secret)
+	SELECT year, split_part(leaderboard, '-', 1), split_part(leaderboard, '-', 2)
+	FROM Years;
+
+ALTER TABLE Years DROP COLUMN leaderboard;
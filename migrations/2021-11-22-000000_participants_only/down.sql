@@ -0,0 +1 @@
+ALTER TABLE Years DROP COLUMN participants_only;
@@ -0,0 +1,3 @@
+-- Whether local scores are recomputed among the registered participants only.
+
+ALTER TABLE Years ADD COLUMN participants_only BOOLEAN NOT NULL DEFAULT FALSE;
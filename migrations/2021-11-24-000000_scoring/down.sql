@@ -0,0 +1,7 @@
+ALTER TABLE Participants DROP COLUMN joined_at;
+
+ALTER TABLE Years
+	DROP COLUMN late_joiners,
+	DROP COLUMN tie_breaker,
+	DROP COLUMN excluded_days,
+	DROP COLUMN points;
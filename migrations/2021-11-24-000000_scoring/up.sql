@@ -0,0 +1,10 @@
+-- The scoring rules of each year, and when participants signed up for it.
+
+ALTER TABLE Years
+	ADD COLUMN points TEXT NOT NULL DEFAULT 'local',
+	ADD COLUMN excluded_days INTEGER[] NOT NULL DEFAULT '{}',
+	ADD COLUMN tie_breaker TEXT NOT NULL DEFAULT 'none',
+	ADD COLUMN late_joiners BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Seconds since the epoch, unknown for those who signed up before it was kept.
+ALTER TABLE Participants ADD COLUMN joined_at BIGINT;
@@ -0,0 +1 @@
+ALTER TABLE Years DROP COLUMN splits_tie_breaker;
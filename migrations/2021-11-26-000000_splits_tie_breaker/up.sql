@@ -0,0 +1,3 @@
+-- Ties on the splits leaderboard are broken separately from the score ones.
+
+ALTER TABLE Years ADD COLUMN splits_tie_breaker TEXT NOT NULL DEFAULT 'none';
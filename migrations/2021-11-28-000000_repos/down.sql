@@ -0,0 +1 @@
+ALTER TABLE Participants RENAME COLUMN repo TO github;
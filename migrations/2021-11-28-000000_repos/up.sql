@@ -0,0 +1,4 @@
+-- Repos may be on other hosts than GitHub, stored as full URLs. GitHub repos
+-- may still be stored as "owner/repo".
+
+ALTER TABLE Participants RENAME COLUMN github TO repo;
@@ -0,0 +1,4 @@
+ALTER TABLE Years
+	DROP COLUMN registration_closes,
+	DROP COLUMN registration_opens,
+	DROP COLUMN starts_at;
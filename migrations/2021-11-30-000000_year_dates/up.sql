@@ -0,0 +1,7 @@
+-- When a year starts, if not when its first puzzle unlocks, and when it takes
+-- registrations, any time if left out. Seconds since the epoch.
+
+ALTER TABLE Years
+	ADD COLUMN starts_at BIGINT,
+	ADD COLUMN registration_opens BIGINT,
+	ADD COLUMN registration_closes BIGINT;
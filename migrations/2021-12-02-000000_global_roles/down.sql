@@ -0,0 +1 @@
+DROP INDEX roles_global_unique;
@@ -0,0 +1,13 @@
+-- Roles without a year apply to every year, and a UNIQUE constraint never
+-- finds rows with NULLs in them equal, so global roles need an index of their
+-- own. Roles given twice before it existed are kept once.
+DELETE FROM Roles a
+	USING Roles b
+	WHERE a.year IS NULL
+		AND b.year IS NULL
+		AND a.subject = b.subject
+		AND a.is_group = b.is_group
+		AND a.role = b.role
+		AND a.id > b.id;
+
+CREATE UNIQUE INDEX roles_global_unique ON Roles (subject, is_group, role) WHERE year IS NULL;
@@ -0,0 +1,321 @@
+use diesel::{query_dsl::QueryDsl, BelongingToDsl, ExpressionMethods, GroupedBy, RunQueryDsl};
+use rocket::{
+	http::Status,
+	serde::{json::serde_json, Serialize},
+	Config,
+	Ignite,
+	Rocket,
+};
+
+use crate::{
+	aoc::LeaderboardSource,
+	auth::{AuthUser, Authorized},
+	code_host::CodeHosts,
+	config::Settings,
+	db::{leaderboards, participants, years, DbConn, Year, YearLeaderboard},
+	domain::{
+		delete_aoc_id,
+		delete_leaderboard,
+		get_leaderboard,
+		get_leaderboard_commits,
+		get_leaderboard_languages,
+		get_leaderboard_splits,
+		get_years,
+		load_settings,
+		purge_leaderboards,
+		purge_profiles,
+		set_aoc_id,
+		set_year,
+		LeaderboardCommitsResponse,
+		LeaderboardDeleteRequest,
+		LeaderboardLanguagesResponse,
+		LeaderboardResponse,
+		LeaderboardSplitsResponse,
+		Role,
+		RoleGrant,
+		YearRequest,
+	},
+	identity::{self, IdentityUser},
+	redis::RedisConn,
+};
+
+// Runs the same code as the server, on behalf of an owner that only exists
+// here. Anyone who can run it already has access to the databases.
+pub struct Admin {
+	rocket: Rocket<Ignite>,
+	conn: DbConn,
+	redis: RedisConn,
+	user: Authorized,
+}
+
+fn error(status: Status) -> String {
+	status.to_string()
+}
+
+fn identity_user(cid: &str) -> IdentityUser {
+	IdentityUser {
+		cid: cid.to_owned(),
+		nick: cid.to_owned(),
+		avatar_url: String::new(),
+		groups: Vec::new(),
+	}
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct YearExport {
+	year: i32,
+	leaderboard: Vec<LeaderboardResponse>,
+	splits: Vec<LeaderboardSplitsResponse>,
+	languages: Vec<LeaderboardLanguagesResponse>,
+	commits: Vec<LeaderboardCommitsResponse>,
+}
+
+impl Admin {
+	pub async fn new() -> Result<Admin, String> {
+		let rocket = crate::custom(Config::figment())
+			.ignite()
+			.await
+			.map_err(|e| format!("Could not start. ({})", e))?;
+		let conn = DbConn::get_one(&rocket)
+			.await
+			.ok_or_else(|| "Database connection not found.".to_owned())?;
+		let redis = RedisConn::get_one(&rocket)
+			.await
+			.ok_or_else(|| "Redis connection not found.".to_owned())?;
+
+		let admin = Admin {
+			user: Authorized {
+				user: AuthUser {
+					user: identity_user("digit-aoc-admin"),
+					via_token: false,
+				},
+				roles: vec![RoleGrant {
+					role: Role::Owner,
+					year: None,
+				}],
+			},
+			rocket,
+			conn,
+			redis,
+		};
+		load_settings(
+			&admin.conn,
+			admin.settings(),
+			admin.leaderboard_source(),
+			admin.code_hosts(),
+		)
+		.await
+		.map_err(|_| "Could not load the settings, are the migrations run?".to_owned())?;
+		Ok(admin)
+	}
+
+	fn settings(&self) -> &Settings {
+		self.rocket.state().unwrap()
+	}
+
+	fn leaderboard_source(&self) -> &dyn LeaderboardSource {
+		self.rocket
+			.state::<Box<dyn LeaderboardSource>>()
+			.unwrap()
+			.as_ref()
+	}
+
+	fn code_hosts(&self) -> &CodeHosts {
+		self.rocket.state().unwrap()
+	}
+
+	pub async fn years(&self) -> Result<Vec<(i32, Vec<String>)>, String> {
+		let (years_db, leaderboards_db) = self
+			.conn
+			.run(|c| {
+				let years_db: Vec<Year> = years::table.order(years::columns::year).load(c)?;
+				let leaderboards_db: Vec<YearLeaderboard> =
+					YearLeaderboard::belonging_to(&years_db)
+						.order(leaderboards::columns::id)
+						.load(c)?;
+				Ok::<_, diesel::result::Error>((years_db, leaderboards_db))
+			})
+			.await
+			.map_err(|e| e.to_string())?;
+		let leaderboards_db = leaderboards_db.grouped_by(&years_db);
+		Ok(years_db
+			.into_iter()
+			.zip(leaderboards_db)
+			.map(|(y, l)| (y.year, l.iter().map(YearLeaderboard::code).collect()))
+			.collect())
+	}
+
+	async fn year_exists(&self, year: i32) -> Result<bool, String> {
+		let years = get_years(&self.conn).await.map_err(error)?;
+		Ok(years.iter().any(|y| y.year == year))
+	}
+
+	pub async fn add_year(&self, year: i32, leaderboard: &str) -> Result<(), String> {
+		if self.year_exists(year).await? {
+			return Err(format!("{} already exists.", year));
+		}
+		self.set_year(year, leaderboard).await
+	}
+
+	pub async fn set_leaderboard(&self, year: i32, leaderboard: &str) -> Result<(), String> {
+		if !self.year_exists(year).await? {
+			return Err(format!("{} doesn't exist.", year));
+		}
+		self.set_year(year, leaderboard).await
+	}
+
+	pub async fn remove_leaderboard(&self, year: i32, leaderboard: &str) -> Result<(), String> {
+		delete_leaderboard(
+			LeaderboardDeleteRequest {
+				year,
+				leaderboard: leaderboard.to_owned(),
+			},
+			&self.conn,
+			&self.user,
+		)
+		.await
+		.map_err(|status| {
+			if status == Status::NotFound {
+				format!("{} has no leaderboard {}.", year, leaderboard)
+			} else {
+				error(status)
+			}
+		})
+	}
+
+	async fn set_year(&self, year: i32, leaderboard: &str) -> Result<(), String> {
+		set_year(
+			YearRequest {
+				year,
+				leaderboard: leaderboard.to_owned(),
+			},
+			&self.conn,
+			&self.user,
+		)
+		.await
+		.map_err(|status| {
+			if status == Status::BadRequest {
+				"The leaderboard code should look like <leaderboard id>-<secret>.".to_owned()
+			} else {
+				error(status)
+			}
+		})
+	}
+
+	pub async fn link_aoc_id(&self, cid: &str, aoc_id: &str) -> Result<(), String> {
+		set_aoc_id(aoc_id.to_owned(), &self.conn, &identity_user(cid))
+			.await
+			.map_err(error)
+	}
+
+	// Unlinking also removes every participation, which is only done when asked
+	// to explicitly.
+	pub async fn unlink_aoc_id(&self, cid: &str, force: bool) -> Result<(), String> {
+		if !force {
+			let owned_cid = cid.to_owned();
+			let years: Vec<i32> = self
+				.conn
+				.run(move |c| {
+					participants::table
+						.select(participants::columns::year)
+						.filter(participants::columns::cid.eq(owned_cid))
+						.order(participants::columns::year)
+						.load(c)
+				})
+				.await
+				.map_err(|e| e.to_string())?;
+			if !years.is_empty() {
+				let years: Vec<_> = years.iter().map(i32::to_string).collect();
+				return Err(format!(
+					"{} participates in {}, use --force to remove them from those years as well.",
+					cid,
+					years.join(", ")
+				));
+			}
+		}
+		delete_aoc_id(&self.conn, &identity_user(cid))
+			.await
+			.map_err(|status| {
+				if status == Status::NotFound {
+					format!("{} has no AoC id.", cid)
+				} else {
+					error(status)
+				}
+			})
+	}
+
+	pub async fn purge(&self, year: Option<i32>) -> Result<usize, String> {
+		let mut purged = purge_leaderboards(&self.redis, year).await.map_err(error)?;
+		if year.is_none() {
+			purged += purge_profiles(&self.redis).await.map_err(error)?;
+		}
+		Ok(purged)
+	}
+
+	async fn export(&self, year: i32) -> Result<YearExport, String> {
+		let identity_provider = identity::provider();
+		let (leaderboard, _, _) = get_leaderboard(
+			year,
+			&self.conn,
+			&self.redis,
+			self.settings(),
+			self.leaderboard_source(),
+			identity_provider,
+		)
+		.await
+		.map_err(error)?;
+		let (splits, _, _) = get_leaderboard_splits(
+			year,
+			&self.conn,
+			&self.redis,
+			self.settings(),
+			self.leaderboard_source(),
+			identity_provider,
+		)
+		.await
+		.map_err(error)?;
+		let (languages, _, _) = get_leaderboard_languages(
+			year,
+			&self.conn,
+			&self.redis,
+			self.settings(),
+			self.leaderboard_source(),
+			identity_provider,
+			self.code_hosts(),
+		)
+		.await
+		.map_err(error)?;
+		let (commits, _, _) = get_leaderboard_commits(
+			year,
+			&self.conn,
+			&self.redis,
+			self.settings(),
+			self.leaderboard_source(),
+			identity_provider,
+			self.code_hosts(),
+		)
+		.await
+		.map_err(error)?;
+		Ok(YearExport {
+			year,
+			leaderboard,
+			splits,
+			languages,
+			commits,
+		})
+	}
+
+	pub async fn refresh(&self, year: i32) -> Result<(), String> {
+		if !self.year_exists(year).await? {
+			return Err(format!("{} doesn't exist.", year));
+		}
+		self.purge(Some(year)).await?;
+		self.export(year).await.map(|_| ())
+	}
+
+	pub async fn export_json(&self, year: i32) -> Result<String, String> {
+		let export = self.export(year).await?;
+		serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+	}
+}
@@ -0,0 +1,74 @@
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	RwLock,
+};
+
+use reqwest::{header::CONTENT_TYPE, Client};
+
+use super::{AocError, Leaderboard, LeaderboardSource};
+
+pub struct AocClient {
+	url: String,
+	session: RwLock<Option<String>>,
+	session_expired: AtomicBool,
+	client: Client,
+}
+
+impl AocClient {
+	// The session is set from the settings once they have been loaded.
+	pub fn new(url: String, client: Client) -> AocClient {
+		AocClient {
+			url,
+			session: RwLock::new(None),
+			session_expired: AtomicBool::new(true),
+			client,
+		}
+	}
+}
+
+#[rocket::async_trait]
+impl LeaderboardSource for AocClient {
+	async fn get_leaderboard(&self, year: i32, leaderboard: &str) -> Result<Leaderboard, AocError> {
+		let session = match self.session.read().unwrap().clone() {
+			Some(session) => session,
+			None => return Err(AocError::SessionExpired),
+		};
+		let response = self
+			.client
+			.get(format!(
+				"{}/{}/leaderboard/private/view/{}.json",
+				self.url, year, leaderboard
+			))
+			.header("Cookie", format!("session={}", session))
+			.send()
+			.await?
+			.error_for_status()?;
+
+		// Without a valid session AoC redirects to an HTML page instead of
+		// answering with JSON.
+		let is_json = response
+			.headers()
+			.get(CONTENT_TYPE)
+			.and_then(|content_type| content_type.to_str().ok())
+			.is_some_and(|content_type| content_type.starts_with("application/json"));
+		if !is_json {
+			self.session_expired.store(true, Ordering::Relaxed);
+			return Err(AocError::SessionExpired);
+		}
+
+		let leaderboard = response.json().await?;
+		self.session_expired.store(false, Ordering::Relaxed);
+		Ok(leaderboard)
+	}
+
+	fn session_expired(&self) -> bool {
+		self.session_expired.load(Ordering::Relaxed)
+	}
+
+	fn set_session(&self, session: Option<String>) -> Result<(), AocError> {
+		self.session_expired
+			.store(session.is_none(), Ordering::Relaxed);
+		*self.session.write().unwrap() = session;
+		Ok(())
+	}
+}
@@ -0,0 +1,215 @@
+mod client;
+mod fixtures;
+
+use std::{
+	collections::HashMap,
+	error::Error,
+	fmt::{self, Display, Formatter},
+	io,
+	path::PathBuf,
+};
+
+pub use client::AocClient;
+pub use fixtures::FixtureSource;
+use rocket::{
+	fairing::{AdHoc, Fairing},
+	http::Status,
+	request::{FromRequest, Outcome},
+	serde::json::serde_json,
+	Request,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::http_client::HttpConfig;
+
+#[rocket::async_trait]
+pub trait LeaderboardSource: Send + Sync {
+	async fn get_leaderboard(&self, year: i32, leaderboard: &str) -> Result<Leaderboard, AocError>;
+
+	fn session_expired(&self) -> bool {
+		false
+	}
+
+	fn set_session(&self, _session: Option<String>) -> Result<(), AocError> {
+		Err(AocError::Unsupported)
+	}
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SourceKind {
+	#[default]
+	Http,
+	Fixtures,
+}
+
+#[derive(Deserialize)]
+struct AocConfig {
+	#[serde(default)]
+	source: SourceKind,
+	#[serde(default = "default_url")]
+	url: String,
+	fixtures: Option<PathBuf>,
+	#[serde(flatten)]
+	http: HttpConfig,
+}
+
+fn default_url() -> String {
+	"https://adventofcode.com".to_owned()
+}
+
+pub fn fairing() -> impl Fairing {
+	AdHoc::try_on_ignite("AoC leaderboard source", |rocket| async move {
+		let config: AocConfig = match rocket.figment().focus("aoc").extract() {
+			Ok(config) => config,
+			Err(e) => {
+				eprintln!("Invalid AoC configuration. ({})", e);
+				return Err(rocket);
+			}
+		};
+
+		let source: Box<dyn LeaderboardSource> = match config.source {
+			SourceKind::Http => {
+				// Advent of Code asks automated tools to say who to contact about
+				// them.
+				if config.http.contact.is_none() {
+					eprintln!(
+						"Set a contact in the AoC configuration, e.g. an email address or a URL."
+					);
+					return Err(rocket);
+				}
+				let client = match config.http.client() {
+					Ok(client) => client,
+					Err(e) => {
+						eprintln!("Could not create the AoC HTTP client. ({})", e);
+						return Err(rocket);
+					}
+				};
+				Box::new(AocClient::new(config.url, client))
+			}
+			SourceKind::Fixtures => Box::new(FixtureSource::new(
+				config.fixtures.unwrap_or_else(|| PathBuf::from("fixtures")),
+			)),
+		};
+
+		Ok(rocket.manage(source))
+	})
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r dyn LeaderboardSource {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		match request.rocket().state::<Box<dyn LeaderboardSource>>() {
+			Some(source) => Outcome::Success(source.as_ref()),
+			None => Outcome::Failure((Status::InternalServerError, ())),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum AocError {
+	SessionExpired,
+	Unsupported,
+	Request(reqwest::Error),
+	Io(io::Error),
+	Parse(serde_json::Error),
+}
+
+impl From<reqwest::Error> for AocError {
+	fn from(err: reqwest::Error) -> Self {
+		AocError::Request(err)
+	}
+}
+
+impl From<io::Error> for AocError {
+	fn from(err: io::Error) -> Self {
+		AocError::Io(err)
+	}
+}
+
+impl From<serde_json::Error> for AocError {
+	fn from(err: serde_json::Error) -> Self {
+		AocError::Parse(err)
+	}
+}
+
+impl Error for AocError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			AocError::SessionExpired | AocError::Unsupported => None,
+			AocError::Request(err) => Some(err),
+			AocError::Io(err) => Some(err),
+			AocError::Parse(err) => Some(err),
+		}
+	}
+}
+
+impl Display for AocError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			AocError::SessionExpired => write!(f, "The AoC session has expired or is missing"),
+			AocError::Unsupported => write!(f, "Not supported by this leaderboard source"),
+			AocError::Request(err) => write!(f, "Request error ({})", err),
+			AocError::Io(err) => write!(f, "Could not read fixture ({})", err),
+			AocError::Parse(err) => write!(f, "Malformatted leaderboard ({})", err),
+		}
+	}
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Leaderboard {
+	pub members: HashMap<String, Member>,
+}
+
+impl Leaderboard {
+	// Puts the members of every leaderboard together. Their local scores still
+	// only count their own leaderboard, so they are recomputed with
+	// `Scoring::rescore` afterwards.
+	pub fn merge(leaderboards: Vec<Leaderboard>) -> Leaderboard {
+		let mut merged = Leaderboard {
+			members: HashMap::new(),
+		};
+		for leaderboard in leaderboards {
+			merged.members.extend(leaderboard.members);
+		}
+		merged
+	}
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Member {
+	pub completion_day_level: HashMap<String, Day>,
+	pub local_score: u16,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Day {
+	#[serde(rename = "1", default, deserialize_with = "from_get_star_ts")]
+	pub first_star_ts: Option<u64>,
+	#[serde(rename = "2", default, deserialize_with = "from_get_star_ts")]
+	pub second_star_ts: Option<u64>,
+}
+
+impl Member {
+	pub fn last_star_ts(&self) -> Option<u64> {
+		self.completion_day_level
+			.values()
+			.flat_map(|d| d.first_star_ts.into_iter().chain(d.second_star_ts))
+			.max()
+	}
+}
+
+fn from_get_star_ts<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+	D: serde::Deserializer<'de>,
+{
+	let s: Option<GetStarTs> = Option::deserialize(deserializer)?;
+	Ok(s.map(|gst| gst.get_star_ts))
+}
+
+#[derive(Deserialize)]
+struct GetStarTs {
+	get_star_ts: u64,
+}
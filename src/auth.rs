@@ -0,0 +1,136 @@
+use std::ops::Deref;
+
+use rocket::{
+	http::Status,
+	outcome::try_outcome,
+	request::{FromRequest, Outcome},
+	Request,
+};
+
+use crate::{
+	config::Settings,
+	db::DbConn,
+	domain::{find_api_token_owner, get_role_grants, remembered_user, Role, RoleGrant},
+	identity::{self, IdentityError, IdentityProvider, IdentityUser},
+	redis::RedisConn,
+};
+
+pub struct AuthUser {
+	pub user: IdentityUser,
+	pub via_token: bool,
+}
+
+impl Deref for AuthUser {
+	type Target = IdentityUser;
+
+	fn deref(&self) -> &Self::Target {
+		&self.user
+	}
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthUser {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		let identity_provider = try_outcome!(request.guard::<&dyn IdentityProvider>().await);
+
+		if let Some(authorization) = request.headers().get_one("Authorization") {
+			let token = match authorization.strip_prefix("Bearer ") {
+				Some(token) => token.trim().to_owned(),
+				None => return Outcome::Failure((Status::Unauthorized, ())),
+			};
+			let conn = try_outcome!(request.guard::<DbConn>().await);
+			let cid = match find_api_token_owner(token, &conn).await {
+				Ok(Some(cid)) => cid,
+				Ok(None) => return Outcome::Failure((Status::Unauthorized, ())),
+				Err(status) => return Outcome::Failure((status, ())),
+			};
+			let user = match identity_provider.get_user(&cid).await {
+				Ok(user) => user,
+				// Users can't be looked up with OpenID Connect, they are who they
+				// were when they last signed in.
+				Err(IdentityError::Unsupported) => {
+					let redis = try_outcome!(request.guard::<RedisConn>().await);
+					remembered_user(&cid, &redis).await
+				}
+				Err(e) => {
+					println!(
+						"Could not get user {} for API token ({}:{})\n\t{:?}",
+						cid,
+						file!(),
+						line!(),
+						e
+					);
+					return Outcome::Failure((Status::Unauthorized, ()));
+				}
+			};
+			return Outcome::Success(AuthUser {
+				user,
+				via_token: true,
+			});
+		}
+
+		let access_cookie = match request.cookies().get(identity::cookie()) {
+			Some(access_cookie) => access_cookie,
+			None => return Outcome::Failure((Status::Unauthorized, ())),
+		};
+		match identity_provider.get_me(access_cookie.value()).await {
+			Ok(user) => Outcome::Success(AuthUser {
+				user,
+				via_token: false,
+			}),
+			Err(_) => Outcome::Failure((Status::Unauthorized, ())),
+		}
+	}
+}
+
+pub struct Authorized {
+	pub user: AuthUser,
+	pub roles: Vec<RoleGrant>,
+}
+
+impl Authorized {
+	// API tokens may moderate, but changing years, roles and settings needs a
+	// signed in session, so that a leaked token can't be used to take over.
+	pub fn has_role(&self, role: Role, year: Option<i32>) -> bool {
+		if self.via_token && matches!(role, Role::Owner | Role::YearAdmin) {
+			return false;
+		}
+		self.roles.iter().any(|grant| grant.allows(role, year))
+	}
+
+	pub fn require(&self, role: Role, year: Option<i32>) -> Result<(), Status> {
+		if self.has_role(role, year) {
+			Ok(())
+		} else {
+			Err(Status::Forbidden)
+		}
+	}
+}
+
+impl Deref for Authorized {
+	type Target = AuthUser;
+
+	fn deref(&self) -> &Self::Target {
+		&self.user
+	}
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authorized {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		let user = try_outcome!(request.guard::<AuthUser>().await);
+		let conn = try_outcome!(request.guard::<DbConn>().await);
+		let settings = try_outcome!(request.guard::<&Settings>().await);
+		match get_role_grants(&user, &conn, settings).await {
+			Ok(roles) => Outcome::Success(Authorized {
+				user,
+				roles,
+			}),
+			Err(status) => Outcome::Failure((status, ())),
+		}
+	}
+}
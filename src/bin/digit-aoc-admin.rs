@@ -0,0 +1,65 @@
+use std::{env, process};
+
+use digit_aoc::admin::Admin;
+
+const USAGE: &str = "Usage: digit-aoc-admin <command>
+
+Commands:
+  years                         List the years and their leaderboard codes
+  add-year <year> <code>        Add a year with the leaderboard code <id>-<secret>
+  set-code <year> <code>        Add a leaderboard to a year, or change the code of
+                                one of its leaderboards
+  remove-code <year> <id>       Remove a leaderboard from a year
+  link <cid> <aoc id>           Link an AoC id to a cid
+  unlink <cid> [--force]        Unlink the AoC id of a cid, --force is needed when
+                                that removes them from years they participate in
+  refresh <year>                Rebuild the cached leaderboards of a year
+  purge [year]                  Remove the cached leaderboards of a year, or every
+                                cached leaderboard and profile
+  export <year>                 Print the results of a year as JSON";
+
+fn parse_year(year: &str) -> Result<i32, String> {
+	year.parse()
+		.map_err(|_| format!("Invalid year \"{}\".", year))
+}
+
+async fn run(args: &[&str]) -> Result<(), String> {
+	if matches!(args, [] | ["help"] | ["--help"]) {
+		println!("{}", USAGE);
+		return Ok(());
+	}
+
+	let admin = Admin::new().await?;
+	match *args {
+		["years"] => {
+			for (year, leaderboards) in admin.years().await? {
+				println!("{}\t{}", year, leaderboards.join(", "));
+			}
+		}
+		["add-year", year, code] => admin.add_year(parse_year(year)?, code).await?,
+		["set-code", year, code] => admin.set_leaderboard(parse_year(year)?, code).await?,
+		["remove-code", year, id] => admin.remove_leaderboard(parse_year(year)?, id).await?,
+		["link", cid, aoc_id] => admin.link_aoc_id(cid, aoc_id).await?,
+		["unlink", cid] => admin.unlink_aoc_id(cid, false).await?,
+		["unlink", cid, "--force"] => admin.unlink_aoc_id(cid, true).await?,
+		["refresh", year] => admin.refresh(parse_year(year)?).await?,
+		["purge"] => println!("Removed {} cached values.", admin.purge(None).await?),
+		["purge", year] => {
+			let purged = admin.purge(Some(parse_year(year)?)).await?;
+			println!("Removed {} cached values.", purged);
+		}
+		["export", year] => println!("{}", admin.export_json(parse_year(year)?).await?),
+		_ => return Err(USAGE.to_owned()),
+	}
+	Ok(())
+}
+
+#[rocket::main]
+async fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+	let args: Vec<&str> = args.iter().map(String::as_str).collect();
+	if let Err(e) = run(&args).await {
+		eprintln!("{}", e);
+		process::exit(1);
+	}
+}
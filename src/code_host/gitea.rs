@@ -0,0 +1,133 @@
+use reqwest::{Client, RequestBuilder};
+use rocket::serde::Deserialize;
+
+use super::{repo_response, CodeHost, CodeHostError, Commit, Repo, TreeEntry};
+use crate::timestamp;
+
+// The most commits Gitea lists at once by default.
+const COMMIT_PAGE: usize = 50;
+
+pub struct GiteaClient {
+	url: String,
+	api_url: String,
+	token: Option<String>,
+	client: Client,
+}
+
+impl GiteaClient {
+	pub fn new(url: String, api_url: String, token: Option<String>, client: Client) -> GiteaClient {
+		GiteaClient {
+			url,
+			api_url,
+			token,
+			client,
+		}
+	}
+
+	fn get(&self, path: &str) -> RequestBuilder {
+		let mut request = self.client.get(format!("{}{}", self.api_url, path));
+		if let Some(token) = &self.token {
+			request = request.header("Authorization", format!("token {}", token));
+		}
+		request
+	}
+}
+
+#[derive(Deserialize)]
+struct GiteaRepo {
+	full_name: String,
+	private: bool,
+	default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaTree {
+	#[serde(default)]
+	tree: Vec<TreeEntry>,
+	#[serde(default)]
+	truncated: bool,
+}
+
+#[rocket::async_trait]
+impl CodeHost for GiteaClient {
+	fn url(&self) -> &str {
+		&self.url
+	}
+
+	// Gitea wants a branch to read the tree of, and pages it.
+	async fn get_tree(&self, repo: &str) -> Result<Vec<TreeEntry>, CodeHostError> {
+		let info: GiteaRepo = self
+			.get(&format!("/repos/{}", repo))
+			.send()
+			.await?
+			.error_for_status()?
+			.json()
+			.await?;
+
+		let mut tree = Vec::new();
+		for page in 1.. {
+			let response: GiteaTree = self
+				.get(&format!(
+					"/repos/{}/git/trees/{}?recursive=true&page={}",
+					repo, info.default_branch, page
+				))
+				.send()
+				.await?
+				.error_for_status()?
+				.json()
+				.await?;
+			let done = !response.truncated || response.tree.is_empty();
+			tree.extend(response.tree);
+			if done {
+				break;
+			}
+		}
+		Ok(tree)
+	}
+
+	// Older versions of Gitea list every commit, newest first, so paging stops
+	// at the first commit from before `since`.
+	async fn get_commit_times(
+		&self,
+		repo: &str,
+		path: &str,
+		since: i64,
+		until: i64,
+	) -> Result<Vec<i64>, CodeHostError> {
+		let mut times = Vec::new();
+		for page in 1.. {
+			let commits: Vec<Commit> = self
+				.get(&format!("/repos/{}/commits", repo))
+				.query(&[
+					("path", path.to_owned()),
+					("since", timestamp::format(since)),
+					("until", timestamp::format(until)),
+					("limit", COMMIT_PAGE.to_string()),
+					("page", page.to_string()),
+					("stat", "false".to_owned()),
+					("verification", "false".to_owned()),
+					("files", "false".to_owned()),
+				])
+				.send()
+				.await?
+				.error_for_status()?
+				.json()
+				.await?;
+			let page_times: Vec<_> = commits.iter().filter_map(Commit::time).collect();
+			let done = commits.len() < COMMIT_PAGE || page_times.iter().any(|&time| time < since);
+			times.extend(page_times);
+			if done {
+				break;
+			}
+		}
+		Ok(times)
+	}
+
+	async fn get_repo(&self, repo: &str) -> Result<Option<Repo>, CodeHostError> {
+		let repo: Option<GiteaRepo> = repo_response(self.get(&format!("/repos/{}", repo))).await?;
+		Ok(repo.map(|repo| Repo {
+			path: repo.full_name,
+			public: !repo.private,
+		}))
+	}
+}
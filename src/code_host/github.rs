@@ -0,0 +1,324 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, RwLock},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+	header::{HeaderMap, ETAG, IF_NONE_MATCH, RETRY_AFTER},
+	Client,
+	RequestBuilder,
+	StatusCode,
+};
+use rocket::{
+	http::RawStr,
+	serde::{json::serde_json, Deserialize, DeserializeOwned},
+};
+
+use super::{CodeHost, CodeHostError, Commit, Repo, TreeEntry};
+use crate::timestamp;
+
+// The most commits GitHub lists at once.
+const COMMIT_PAGE: usize = 100;
+// The most answers that are kept to ask for again. Commits are listed for
+// every day of every participant, so they are kept apart from the trees and
+// repos and don't push them out.
+const MAX_RESPONSES: usize = 4096;
+const MAX_COMMIT_RESPONSES: usize = 8192;
+
+pub struct GitHubClient {
+	url: String,
+	api_url: String,
+	credentials: RwLock<Option<Credentials>>,
+	client: Client,
+	// The latest answers to recently asked paths, asked for again with their
+	// ETag. GitHub doesn't count answers that nothing has changed against the
+	// rate limit.
+	responses: Mutex<Responses>,
+	commit_responses: Mutex<Responses>,
+	rate_limit: Mutex<Option<RateLimit>>,
+}
+
+// A token is a personal access token or the installation token of a GitHub
+// App, an OAuth app identifies itself with its client id and secret.
+enum Credentials {
+	Token(String),
+	Client(String, String),
+}
+
+struct CachedResponse {
+	etag: Option<String>,
+	body: Vec<u8>,
+}
+
+// Once full, the answer that was used the longest ago makes room.
+struct Responses {
+	entries: HashMap<String, (u64, Arc<CachedResponse>)>,
+	uses: u64,
+	capacity: usize,
+}
+
+impl Responses {
+	fn new(capacity: usize) -> Responses {
+		Responses {
+			entries: HashMap::new(),
+			uses: 0,
+			capacity,
+		}
+	}
+
+	fn get(&mut self, path: &str) -> Option<Arc<CachedResponse>> {
+		self.uses += 1;
+		let uses = self.uses;
+		self.entries.get_mut(path).map(|(used, response)| {
+			*used = uses;
+			Arc::clone(response)
+		})
+	}
+
+	fn insert(&mut self, path: String, response: CachedResponse) {
+		if self.entries.len() >= self.capacity && !self.entries.contains_key(&path) {
+			let least_used = self
+				.entries
+				.iter()
+				.min_by_key(|(_, (used, _))| *used)
+				.map(|(path, _)| path.to_owned());
+			if let Some(least_used) = least_used {
+				self.entries.remove(&least_used);
+			}
+		}
+		self.uses += 1;
+		self.entries.insert(path, (self.uses, Arc::new(response)));
+	}
+
+	fn remove(&mut self, path: &str) {
+		self.entries.remove(path);
+	}
+}
+
+#[derive(Clone, Copy)]
+struct RateLimit {
+	remaining: u64,
+	// When the quota is renewed, in seconds since the epoch.
+	reset: u64,
+}
+
+impl GitHubClient {
+	// The credentials are set from the settings once they have been loaded.
+	pub fn new(url: String, api_url: String, client: Client) -> GitHubClient {
+		GitHubClient {
+			url,
+			api_url,
+			credentials: RwLock::new(None),
+			client,
+			responses: Mutex::new(Responses::new(MAX_RESPONSES)),
+			commit_responses: Mutex::new(Responses::new(MAX_COMMIT_RESPONSES)),
+			rate_limit: Mutex::new(None),
+		}
+	}
+
+	// A token takes precedence over the client id and secret.
+	pub fn set_credentials(
+		&self,
+		token: Option<String>,
+		client_id: Option<String>,
+		client_secret: Option<String>,
+	) {
+		*self.credentials.write().unwrap() = token.map(Credentials::Token).or_else(|| {
+			client_id
+				.zip(client_secret)
+				.map(|(id, secret)| Credentials::Client(id, secret))
+		});
+	}
+
+	fn get(&self, path: &str) -> RequestBuilder {
+		let request = self.client.get(format!("{}{}", self.api_url, path));
+		// Without credentials GitHub still answers, but with a far lower rate
+		// limit. They are only ever sent in the Authorization header, never in
+		// the URL, so that they can't end up in logged errors.
+		match self.credentials.read().unwrap().as_ref() {
+			Some(Credentials::Token(token)) => request.bearer_auth(token),
+			Some(Credentials::Client(client_id, client_secret)) => {
+				request.basic_auth(client_id, Some(client_secret))
+			}
+			None => request,
+		}
+	}
+
+	// Asks GitHub for a path, None if there is nothing there. While the rate
+	// limit is exhausted nothing is asked, and the latest answer is used
+	// instead if there is one.
+	async fn get_json<T: DeserializeOwned>(
+		&self,
+		responses: &Mutex<Responses>,
+		path: &str,
+	) -> Result<Option<T>, CodeHostError> {
+		let cached = responses.lock().unwrap().get(path);
+		if self.exhausted() {
+			let cached = cached.ok_or(CodeHostError::RateLimited)?;
+			return Ok(Some(serde_json::from_slice(&cached.body)?));
+		}
+
+		let mut request = self.get(path);
+		if let Some(etag) = cached.as_ref().and_then(|cached| cached.etag.as_ref()) {
+			request = request.header(IF_NONE_MATCH, etag);
+		}
+		let response = request.send().await?;
+		self.track_rate_limit(response.headers());
+		match (response.status(), cached) {
+			(StatusCode::NOT_MODIFIED, Some(cached)) => {
+				return Ok(Some(serde_json::from_slice(&cached.body)?));
+			}
+			(StatusCode::NOT_FOUND, _) => {
+				responses.lock().unwrap().remove(path);
+				return Ok(None);
+			}
+			(StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS, Some(cached))
+				if self.exhausted() =>
+			{
+				return Ok(Some(serde_json::from_slice(&cached.body)?));
+			}
+			(StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS, None) if self.exhausted() => {
+				return Err(CodeHostError::RateLimited);
+			}
+			_ => {}
+		}
+
+		let response = response.error_for_status()?;
+		let etag = response
+			.headers()
+			.get(ETAG)
+			.and_then(|etag| etag.to_str().ok())
+			.map(str::to_owned);
+		let body = response.bytes().await?.to_vec();
+		let value = serde_json::from_slice(&body)?;
+		responses
+			.lock()
+			.unwrap()
+			.insert(path.to_owned(), CachedResponse {
+				etag,
+				body,
+			});
+		Ok(Some(value))
+	}
+
+	// GitHub tells how many requests are left until the quota is renewed, or
+	// how long to wait when asked too often.
+	fn track_rate_limit(&self, headers: &HeaderMap) {
+		let header = |name: &str| {
+			headers
+				.get(name)
+				.and_then(|value| value.to_str().ok())
+				.and_then(|value| value.parse::<u64>().ok())
+		};
+		let rate_limit = match header(RETRY_AFTER.as_str()) {
+			Some(seconds) => Some(RateLimit {
+				remaining: 0,
+				reset: now() + seconds,
+			}),
+			None => header("x-ratelimit-remaining")
+				.zip(header("x-ratelimit-reset"))
+				.map(|(remaining, reset)| RateLimit {
+					remaining,
+					reset,
+				}),
+		};
+		if let Some(rate_limit) = rate_limit {
+			if rate_limit.remaining == 0 && !self.exhausted() {
+				println!(
+					"GitHub rate limit exhausted, backing off for {} seconds ({}:{})",
+					rate_limit.reset.saturating_sub(now()),
+					file!(),
+					line!()
+				);
+			}
+			*self.rate_limit.lock().unwrap() = Some(rate_limit);
+		}
+	}
+
+	fn exhausted(&self) -> bool {
+		matches!(
+			*self.rate_limit.lock().unwrap(),
+			Some(RateLimit { remaining: 0, reset }) if now() < reset
+		)
+	}
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |now| now.as_secs())
+}
+
+#[derive(Deserialize)]
+struct Tree {
+	tree: Vec<TreeEntry>,
+}
+
+#[derive(Deserialize)]
+struct GitHubRepo {
+	full_name: String,
+	private: bool,
+}
+
+#[rocket::async_trait]
+impl CodeHost for GitHubClient {
+	fn url(&self) -> &str {
+		&self.url
+	}
+
+	// Every file of the default branch. Very large trees are truncated by
+	// GitHub, which only leaves out files further down.
+	async fn get_tree(&self, repo: &str) -> Result<Vec<TreeEntry>, CodeHostError> {
+		let tree: Tree = self
+			.get_json(
+				&self.responses,
+				&format!("/repos/{}/git/trees/HEAD?recursive=1", repo),
+			)
+			.await?
+			.ok_or(CodeHostError::NotFound)?;
+		Ok(tree.tree)
+	}
+
+	async fn get_commit_times(
+		&self,
+		repo: &str,
+		path: &str,
+		since: i64,
+		until: i64,
+	) -> Result<Vec<i64>, CodeHostError> {
+		let mut times = Vec::new();
+		for page in 1.. {
+			let commits: Vec<Commit> = self
+				.get_json(
+					&self.commit_responses,
+					&format!(
+						"/repos/{}/commits?path={}&since={}&until={}&per_page={}&page={}",
+						repo,
+						RawStr::new(path).percent_encode(),
+						timestamp::format(since),
+						timestamp::format(until),
+						COMMIT_PAGE,
+						page
+					),
+				)
+				.await?
+				.ok_or(CodeHostError::NotFound)?;
+			times.extend(commits.iter().filter_map(Commit::time));
+			if commits.len() < COMMIT_PAGE {
+				break;
+			}
+		}
+		Ok(times)
+	}
+
+	async fn get_repo(&self, repo: &str) -> Result<Option<Repo>, CodeHostError> {
+		let repo: Option<GitHubRepo> = self
+			.get_json(&self.responses, &format!("/repos/{}", repo))
+			.await?;
+		Ok(repo.map(|repo| Repo {
+			path: repo.full_name,
+			public: !repo.private,
+		}))
+	}
+}
@@ -0,0 +1,162 @@
+use reqwest::{Client, RequestBuilder, Response};
+use rocket::serde::Deserialize;
+
+use super::{repo_response, CodeHost, CodeHostError, Repo, TreeEntry};
+use crate::timestamp;
+
+pub struct GitLabClient {
+	url: String,
+	api_url: String,
+	token: Option<String>,
+	client: Client,
+}
+
+impl GitLabClient {
+	pub fn new(
+		url: String,
+		api_url: String,
+		token: Option<String>,
+		client: Client,
+	) -> GitLabClient {
+		GitLabClient {
+			url,
+			api_url,
+			token,
+			client,
+		}
+	}
+
+	// Projects in groups have more than two parts, the whole path is the id of
+	// the project.
+	fn get(&self, repo: &str, path: &str) -> RequestBuilder {
+		let mut request = self.client.get(format!(
+			"{}/projects/{}{}",
+			self.api_url,
+			repo.replace('/', "%2F"),
+			path
+		));
+		if let Some(token) = &self.token {
+			request = request.header("PRIVATE-TOKEN", token);
+		}
+		request
+	}
+}
+
+#[derive(Deserialize)]
+struct GitLabProject {
+	path_with_namespace: String,
+	visibility: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabEntry {
+	path: String,
+	#[serde(rename = "type")]
+	kind: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabCommit {
+	committed_date: String,
+}
+
+#[rocket::async_trait]
+impl CodeHost for GitLabClient {
+	fn url(&self) -> &str {
+		&self.url
+	}
+
+	// GitLab pages the tree and doesn't tell the sizes of the files.
+	async fn get_tree(&self, repo: &str) -> Result<Vec<TreeEntry>, CodeHostError> {
+		let mut tree = Vec::new();
+		let mut page = "1".to_owned();
+		loop {
+			let response = self
+				.get(repo, "/repository/tree")
+				.query(&[
+					("recursive", "true"),
+					("per_page", "100"),
+					("page", page.as_str()),
+				])
+				.send()
+				.await?
+				.error_for_status()?;
+			let next_page = next_page(&response);
+			let entries: Vec<GitLabEntry> = response.json().await?;
+			tree.extend(entries.into_iter().map(|entry| TreeEntry {
+				path: entry.path,
+				kind: entry.kind,
+				size: None,
+			}));
+
+			match next_page {
+				Some(next_page) => page = next_page,
+				None => return Ok(tree),
+			}
+		}
+	}
+
+	async fn get_commit_times(
+		&self,
+		repo: &str,
+		path: &str,
+		since: i64,
+		until: i64,
+	) -> Result<Vec<i64>, CodeHostError> {
+		let since = timestamp::format(since);
+		let until = timestamp::format(until);
+		let mut times = Vec::new();
+		let mut page = "1".to_owned();
+		loop {
+			let response = self
+				.get(repo, "/repository/commits")
+				.query(&[
+					("path", path),
+					("since", since.as_str()),
+					("until", until.as_str()),
+					("per_page", "100"),
+					("page", page.as_str()),
+				])
+				.send()
+				.await?
+				.error_for_status()?;
+			let next_page = next_page(&response);
+			let commits: Vec<GitLabCommit> = response.json().await?;
+			times.extend(
+				commits
+					.iter()
+					.filter_map(|commit| timestamp::parse(&commit.committed_date)),
+			);
+
+			match next_page {
+				Some(next_page) => page = next_page,
+				None => return Ok(times),
+			}
+		}
+	}
+
+	// Internal projects can be read with the token, so only private ones are
+	// left out.
+	async fn get_repo(&self, repo: &str) -> Result<Option<Repo>, CodeHostError> {
+		let project: Option<GitLabProject> = repo_response(self.get(repo, "")).await?;
+		Ok(project.map(|project| Repo {
+			path: project.path_with_namespace,
+			public: project.visibility != "private",
+		}))
+	}
+
+	// Pages within a project are under "/-/", as groups may be nested.
+	fn repo_path<'p>(&self, path: &'p str) -> &'p str {
+		path.split_once("/-/").map_or(path, |(repo, _)| repo)
+	}
+}
+
+// GitLab tells the next page in a header, which is empty on the last page.
+fn next_page(response: &Response) -> Option<String> {
+	response
+		.headers()
+		.get("X-Next-Page")
+		.and_then(|next_page| next_page.to_str().ok())
+		.filter(|next_page| !next_page.is_empty())
+		.map(str::to_owned)
+}
@@ -0,0 +1,359 @@
+mod gitea;
+mod github;
+mod gitlab;
+
+use std::{
+	error::Error,
+	fmt::{self, Display, Formatter},
+	iter,
+};
+
+use gitea::GiteaClient;
+pub use github::GitHubClient;
+use gitlab::GitLabClient;
+use reqwest::{RequestBuilder, StatusCode, Url};
+use rocket::{
+	fairing::{AdHoc, Fairing},
+	http::Status,
+	request::{FromRequest, Outcome},
+	serde::{json::serde_json, Deserialize, DeserializeOwned},
+	Request,
+};
+
+use crate::{http_client::HttpConfig, timestamp};
+
+// Where participants keep their solutions. Repos are named by their path on
+// the host, like "owner/repo".
+#[rocket::async_trait]
+pub trait CodeHost: Send + Sync {
+	// The web address repos are found under, like "https://github.com".
+	fn url(&self) -> &str;
+
+	// Every file and directory of the default branch.
+	async fn get_tree(&self, repo: &str) -> Result<Vec<TreeEntry>, CodeHostError>;
+
+	// When the commits made between `since` and `until` that change `path`
+	// were committed. Commit dates are whatever the committer's clock said, so
+	// they can be made up.
+	async fn get_commit_times(
+		&self,
+		repo: &str,
+		path: &str,
+		since: i64,
+		until: i64,
+	) -> Result<Vec<i64>, CodeHostError>;
+
+	// Looks up a repo, None if there is no such repo or it's hidden from us.
+	async fn get_repo(&self, repo: &str) -> Result<Option<Repo>, CodeHostError>;
+
+	// The repo a path on the host is in, leaving out pages within the repo
+	// like "owner/repo/tree/main".
+	fn repo_path<'p>(&self, path: &'p str) -> &'p str {
+		match path.match_indices('/').nth(1) {
+			Some((i, _)) => &path[..i],
+			None => path,
+		}
+	}
+}
+
+// A repo as the host names it.
+pub struct Repo {
+	pub path: String,
+	pub public: bool,
+}
+
+#[derive(Deserialize)]
+pub struct TreeEntry {
+	pub path: String,
+	// "blob" for files and "tree" for directories.
+	#[serde(rename = "type")]
+	pub kind: String,
+	// Not every host tells the sizes of files.
+	#[serde(default)]
+	pub size: Option<u64>,
+}
+
+// GitHub and Gitea list commits alike.
+#[derive(Deserialize)]
+struct Commit {
+	commit: CommitDetails,
+}
+
+#[derive(Deserialize)]
+struct CommitDetails {
+	committer: Signature,
+}
+
+#[derive(Deserialize)]
+struct Signature {
+	date: String,
+}
+
+impl Commit {
+	fn time(&self) -> Option<i64> {
+		timestamp::parse(&self.commit.committer.date)
+	}
+}
+
+// GitHub is always known, other hosts are configured.
+pub struct CodeHosts {
+	github: GitHubClient,
+	others: Vec<Box<dyn CodeHost>>,
+}
+
+impl CodeHosts {
+	pub fn github(&self) -> &GitHubClient {
+		&self.github
+	}
+
+	// Finds the host of a repo and the path of the repo on it. Repos are
+	// given as URLs, or as "owner/repo" on GitHub.
+	pub fn find<'r>(&self, repo: &'r str) -> Option<(&dyn CodeHost, &'r str)> {
+		if !repo.contains("://") {
+			return Some((&self.github, repo));
+		}
+		iter::once(&self.github as &dyn CodeHost)
+			.chain(self.others.iter().map(|host| host.as_ref()))
+			.find_map(|host| {
+				let path = without_scheme(repo)
+					.strip_prefix(without_scheme(host.url()).trim_end_matches('/'))?
+					.strip_prefix('/')?
+					.trim_end_matches('/');
+				(!path.is_empty()).then(|| (host, host.repo_path(path)))
+			})
+	}
+
+	pub async fn get_tree(&self, repo: &str) -> Result<Vec<TreeEntry>, CodeHostError> {
+		let (host, path) = self.find(repo).ok_or(CodeHostError::UnknownHost)?;
+		host.get_tree(path).await
+	}
+
+	pub async fn get_commit_times(
+		&self,
+		repo: &str,
+		path: &str,
+		since: i64,
+		until: i64,
+	) -> Result<Vec<i64>, CodeHostError> {
+		let (host, repo_path) = self.find(repo).ok_or(CodeHostError::UnknownHost)?;
+		host.get_commit_times(repo_path, path, since, until).await
+	}
+}
+
+fn without_scheme(url: &str) -> &str {
+	url.split_once("://").map_or(url, |(_, rest)| rest)
+}
+
+// Where to link to a repo.
+pub fn web_url(repo: &str) -> String {
+	if repo.is_empty() || repo.contains("://") {
+		repo.to_owned()
+	} else {
+		format!("https://github.com/{}", repo)
+	}
+}
+
+// Hosts answer with 404 for repos that don't exist, and often for private
+// ones as well.
+async fn repo_response<T: DeserializeOwned>(
+	request: RequestBuilder,
+) -> Result<Option<T>, CodeHostError> {
+	let response = request.send().await?;
+	if response.status() == StatusCode::NOT_FOUND {
+		return Ok(None);
+	}
+	Ok(Some(response.error_for_status()?.json().await?))
+}
+
+#[derive(Debug)]
+pub enum CodeHostError {
+	UnknownHost,
+	NotFound,
+	// The host doesn't take more requests for a while, and had not been
+	// asked for this before.
+	RateLimited,
+	Request(reqwest::Error),
+	// A request error whose URL told credentials, with them left out.
+	Redacted(String),
+	Json(serde_json::Error),
+}
+
+// Errors are logged with the URL they were for, which must not tell any
+// credentials.
+impl From<reqwest::Error> for CodeHostError {
+	fn from(err: reqwest::Error) -> Self {
+		if let Some(url) = err.url() {
+			let mut redacted = url.clone();
+			if redact_url(&mut redacted) {
+				return CodeHostError::Redacted(
+					err.to_string().replace(url.as_str(), redacted.as_str()),
+				);
+			}
+		}
+		CodeHostError::Request(err)
+	}
+}
+
+// Drops the user name and password from a URL, telling whether it had any.
+fn redact_url(url: &mut Url) -> bool {
+	if url.username().is_empty() && url.password().is_none() {
+		return false;
+	}
+	let _ = url.set_username("");
+	let _ = url.set_password(None);
+	true
+}
+
+impl From<serde_json::Error> for CodeHostError {
+	fn from(err: serde_json::Error) -> Self {
+		CodeHostError::Json(err)
+	}
+}
+
+impl Error for CodeHostError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			CodeHostError::UnknownHost
+			| CodeHostError::NotFound
+			| CodeHostError::RateLimited
+			| CodeHostError::Redacted(_) => None,
+			CodeHostError::Request(err) => Some(err),
+			CodeHostError::Json(err) => Some(err),
+		}
+	}
+}
+
+impl Display for CodeHostError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			CodeHostError::UnknownHost => write!(f, "The repo is not on any known host"),
+			CodeHostError::NotFound => write!(f, "The repo was not found"),
+			CodeHostError::RateLimited => write!(f, "The rate limit is exhausted"),
+			CodeHostError::Request(err) => write!(f, "Request error ({})", err),
+			CodeHostError::Redacted(message) => write!(f, "Request error ({})", message),
+			CodeHostError::Json(err) => write!(f, "Malformed response ({})", err),
+		}
+	}
+}
+
+#[derive(Deserialize)]
+struct GitHubConfig {
+	#[serde(default = "default_github_url")]
+	web_url: String,
+	#[serde(default = "default_github_api_url")]
+	url: String,
+	#[serde(flatten)]
+	http: HttpConfig,
+}
+
+fn default_github_url() -> String {
+	"https://github.com".to_owned()
+}
+
+fn default_github_api_url() -> String {
+	"https://api.github.com".to_owned()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HostKind {
+	GitLab,
+	Gitea,
+}
+
+#[derive(Deserialize)]
+struct HostConfig {
+	kind: HostKind,
+	url: String,
+	api_url: Option<String>,
+	token: Option<String>,
+	#[serde(flatten)]
+	http: HttpConfig,
+}
+
+#[derive(Deserialize)]
+struct CodeHostsConfig {
+	#[serde(default)]
+	code_hosts: Vec<HostConfig>,
+}
+
+pub fn fairing() -> impl Fairing {
+	AdHoc::try_on_ignite("Code hosts", |rocket| async move {
+		let config: GitHubConfig = match rocket.figment().focus("github").extract() {
+			Ok(config) => config,
+			Err(e) => {
+				eprintln!("Invalid GitHub configuration. ({})", e);
+				return Err(rocket);
+			}
+		};
+		let client = match config.http.client() {
+			Ok(client) => client,
+			Err(e) => {
+				eprintln!("Could not create the GitHub HTTP client. ({})", e);
+				return Err(rocket);
+			}
+		};
+		// Credentials in the URL are from before they were given as settings.
+		let mut api_url = config.url;
+		if let Ok(mut url) = Url::parse(&api_url) {
+			if redact_url(&mut url) {
+				eprintln!(
+					"Ignoring the credentials in the GitHub API URL, set github-token or \
+					 github-client-id and github-client-secret instead."
+				);
+				api_url = url.as_str().trim_end_matches('/').to_owned();
+			}
+		}
+		let github = GitHubClient::new(config.web_url, api_url, client);
+
+		let config: CodeHostsConfig = match rocket.figment().extract() {
+			Ok(config) => config,
+			Err(e) => {
+				eprintln!("Invalid code host configuration. ({})", e);
+				return Err(rocket);
+			}
+		};
+		let mut others: Vec<Box<dyn CodeHost>> = Vec::new();
+		for host in config.code_hosts {
+			let client = match host.http.client() {
+				Ok(client) => client,
+				Err(e) => {
+					eprintln!("Could not create the HTTP client for {}. ({})", host.url, e);
+					return Err(rocket);
+				}
+			};
+			let url = host.url.trim_end_matches('/').to_owned();
+			others.push(match host.kind {
+				HostKind::GitLab => Box::new(GitLabClient::new(
+					url.clone(),
+					host.api_url.unwrap_or_else(|| format!("{}/api/v4", url)),
+					host.token,
+					client,
+				)),
+				HostKind::Gitea => Box::new(GiteaClient::new(
+					url.clone(),
+					host.api_url.unwrap_or_else(|| format!("{}/api/v1", url)),
+					host.token,
+					client,
+				)),
+			});
+		}
+
+		Ok(rocket.manage(CodeHosts {
+			github,
+			others,
+		}))
+	})
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r CodeHosts {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		match request.rocket().state::<CodeHosts>() {
+			Some(hosts) => Outcome::Success(hosts),
+			None => Outcome::Failure((Status::InternalServerError, ())),
+		}
+	}
+}
@@ -0,0 +1,350 @@
+use std::{collections::HashMap, env, str::FromStr, sync::RwLock};
+
+use rocket::{
+	fairing::{AdHoc, Fairing},
+	form::FromFormField,
+	http::Status,
+	request::{FromRequest, Outcome},
+	serde::{Deserialize, Serialize},
+	Build,
+	Request,
+	Rocket,
+};
+
+// The longest commit window, a year in hours.
+const MAX_COMMIT_WINDOW: i64 = 366 * 24;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, FromFormField, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SettingKey {
+	#[field(value = "leaderboard-cache-time")]
+	LeaderboardCacheTime,
+	#[field(value = "leaderboard-splits-cache-time")]
+	LeaderboardSplitsCacheTime,
+	#[field(value = "leaderboard-languages-cache-time")]
+	LeaderboardLanguagesCacheTime,
+	#[field(value = "leaderboard-commits-cache-time")]
+	LeaderboardCommitsCacheTime,
+	#[field(value = "profile-cache-time")]
+	ProfileCacheTime,
+	#[field(value = "owner-groups")]
+	OwnerGroups,
+	#[field(value = "aoc-session")]
+	AocSession,
+	#[field(value = "github-client-id")]
+	GithubClientId,
+	#[field(value = "github-client-secret")]
+	GithubClientSecret,
+	#[field(value = "github-token")]
+	GithubToken,
+	#[field(value = "solution-patterns")]
+	SolutionPatterns,
+	#[field(value = "language-allowlist")]
+	LanguageAllowlist,
+	#[field(value = "language-denylist")]
+	LanguageDenylist,
+	#[field(value = "language-min-bytes")]
+	LanguageMinBytes,
+	#[field(value = "languages-per-star")]
+	LanguagesPerStar,
+	#[field(value = "commit-window")]
+	CommitWindow,
+}
+
+impl SettingKey {
+	pub const ALL: [SettingKey; 16] = [
+		SettingKey::LeaderboardCacheTime,
+		SettingKey::LeaderboardSplitsCacheTime,
+		SettingKey::LeaderboardLanguagesCacheTime,
+		SettingKey::LeaderboardCommitsCacheTime,
+		SettingKey::ProfileCacheTime,
+		SettingKey::OwnerGroups,
+		SettingKey::AocSession,
+		SettingKey::GithubClientId,
+		SettingKey::GithubClientSecret,
+		SettingKey::GithubToken,
+		SettingKey::SolutionPatterns,
+		SettingKey::LanguageAllowlist,
+		SettingKey::LanguageDenylist,
+		SettingKey::LanguageMinBytes,
+		SettingKey::LanguagesPerStar,
+		SettingKey::CommitWindow,
+	];
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SettingKey::LeaderboardCacheTime => "leaderboard-cache-time",
+			SettingKey::LeaderboardSplitsCacheTime => "leaderboard-splits-cache-time",
+			SettingKey::LeaderboardLanguagesCacheTime => "leaderboard-languages-cache-time",
+			SettingKey::LeaderboardCommitsCacheTime => "leaderboard-commits-cache-time",
+			SettingKey::ProfileCacheTime => "profile-cache-time",
+			SettingKey::OwnerGroups => "owner-groups",
+			SettingKey::AocSession => "aoc-session",
+			SettingKey::GithubClientId => "github-client-id",
+			SettingKey::GithubClientSecret => "github-client-secret",
+			SettingKey::GithubToken => "github-token",
+			SettingKey::SolutionPatterns => "solution-patterns",
+			SettingKey::LanguageAllowlist => "language-allowlist",
+			SettingKey::LanguageDenylist => "language-denylist",
+			SettingKey::LanguageMinBytes => "language-min-bytes",
+			SettingKey::LanguagesPerStar => "languages-per-star",
+			SettingKey::CommitWindow => "commit-window",
+		}
+	}
+
+	pub fn is_secret(&self) -> bool {
+		matches!(
+			self,
+			SettingKey::AocSession | SettingKey::GithubClientSecret | SettingKey::GithubToken
+		)
+	}
+
+	// Cache times are counted in seconds and must be positive, Redis refuses
+	// to expire keys after zero seconds. The commit window is counted in
+	// hours and must be positive as well. Solution patterns need a place for
+	// the day. Lists may be empty.
+	pub fn normalize(&self, value: &str) -> Option<String> {
+		let value = value.trim();
+		match self {
+			SettingKey::LeaderboardCacheTime
+			| SettingKey::LeaderboardSplitsCacheTime
+			| SettingKey::LeaderboardLanguagesCacheTime
+			| SettingKey::LeaderboardCommitsCacheTime
+			| SettingKey::ProfileCacheTime => value
+				.parse::<usize>()
+				.ok()
+				.filter(|&secs| secs > 0)
+				.map(|secs| secs.to_string()),
+			SettingKey::CommitWindow => value
+				.parse::<i64>()
+				.ok()
+				.filter(|hours| (1..=MAX_COMMIT_WINDOW).contains(hours))
+				.map(|hours| hours.to_string()),
+			SettingKey::LanguageMinBytes => {
+				value.parse::<u64>().ok().map(|bytes| bytes.to_string())
+			}
+			SettingKey::LanguagesPerStar => value.parse::<bool>().ok().map(|on| on.to_string()),
+			// Items are trimmed and empty ones dropped. An empty list of owner
+			// groups is refused.
+			SettingKey::OwnerGroups
+			| SettingKey::LanguageAllowlist
+			| SettingKey::LanguageDenylist => Some(
+				value
+					.split(',')
+					.map(str::trim)
+					.filter(|group| !group.is_empty())
+					.collect::<Vec<_>>()
+					.join(","),
+			)
+			.filter(|groups| !groups.is_empty() || *self != SettingKey::OwnerGroups),
+			SettingKey::SolutionPatterns => {
+				let patterns: Vec<_> = value
+					.split(',')
+					.map(|pattern| pattern.trim().to_lowercase())
+					.filter(|pattern| !pattern.is_empty())
+					.collect();
+				if patterns.is_empty() || patterns.iter().any(|p| p.matches("{day}").count() != 1) {
+					None
+				} else {
+					Some(patterns.join(","))
+				}
+			}
+			_ => Some(value.to_owned()).filter(|value| !value.is_empty()),
+		}
+	}
+}
+
+impl FromStr for SettingKey {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		SettingKey::ALL
+			.iter()
+			.find(|key| key.as_str() == s)
+			.copied()
+			.ok_or(())
+	}
+}
+
+pub struct Settings {
+	defaults: HashMap<SettingKey, String>,
+	overrides: RwLock<HashMap<SettingKey, String>>,
+}
+
+impl Settings {
+	pub fn new(defaults: HashMap<SettingKey, String>) -> Settings {
+		Settings {
+			defaults,
+			overrides: RwLock::new(HashMap::new()),
+		}
+	}
+
+	pub fn get(&self, key: SettingKey) -> Option<String> {
+		self.overrides
+			.read()
+			.unwrap()
+			.get(&key)
+			.or_else(|| self.defaults.get(&key))
+			.cloned()
+	}
+
+	pub fn is_overridden(&self, key: SettingKey) -> bool {
+		self.overrides.read().unwrap().contains_key(&key)
+	}
+
+	pub fn set_override(&self, key: SettingKey, value: Option<String>) {
+		let mut overrides = self.overrides.write().unwrap();
+		match value {
+			Some(value) => overrides.insert(key, value),
+			None => overrides.remove(&key),
+		};
+	}
+
+	// Cache times always have a default and are validated when saved.
+	fn cache_time(&self, key: SettingKey) -> usize {
+		self.get(key)
+			.and_then(|value| value.parse().ok())
+			.unwrap_or(60)
+	}
+
+	pub fn leaderboard_cache_time(&self) -> usize {
+		self.cache_time(SettingKey::LeaderboardCacheTime)
+	}
+
+	pub fn leaderboard_splits_cache_time(&self) -> usize {
+		self.cache_time(SettingKey::LeaderboardSplitsCacheTime)
+	}
+
+	pub fn leaderboard_languages_cache_time(&self) -> usize {
+		self.cache_time(SettingKey::LeaderboardLanguagesCacheTime)
+	}
+
+	pub fn leaderboard_commits_cache_time(&self) -> usize {
+		self.cache_time(SettingKey::LeaderboardCommitsCacheTime)
+	}
+
+	pub fn profile_cache_time(&self) -> usize {
+		self.cache_time(SettingKey::ProfileCacheTime)
+	}
+
+	fn list(&self, key: SettingKey) -> Vec<String> {
+		self.get(key)
+			.map(|list| {
+				list.split(',')
+					.filter(|item| !item.is_empty())
+					.map(str::to_owned)
+					.collect()
+			})
+			.unwrap_or_default()
+	}
+
+	pub fn solution_patterns(&self) -> Vec<String> {
+		self.list(SettingKey::SolutionPatterns)
+	}
+
+	pub fn language_allowlist(&self) -> Vec<String> {
+		self.list(SettingKey::LanguageAllowlist)
+	}
+
+	pub fn language_denylist(&self) -> Vec<String> {
+		self.list(SettingKey::LanguageDenylist)
+	}
+
+	pub fn language_min_bytes(&self) -> u64 {
+		self.get(SettingKey::LanguageMinBytes)
+			.and_then(|value| value.parse().ok())
+			.unwrap_or(0)
+	}
+
+	pub fn languages_per_star(&self) -> bool {
+		self.get(SettingKey::LanguagesPerStar)
+			.is_some_and(|value| value == "true")
+	}
+
+	// In seconds. Values from the environment aren't normalized, so they are
+	// capped here as well.
+	pub fn commit_window(&self) -> i64 {
+		self.get(SettingKey::CommitWindow)
+			.and_then(|value| value.parse::<i64>().ok())
+			.unwrap_or(24)
+			.clamp(1, MAX_COMMIT_WINDOW)
+			* 60 * 60
+	}
+
+	pub fn is_owner_group(&self, group: &str) -> bool {
+		self.get(SettingKey::OwnerGroups)
+			.is_some_and(|groups| groups.split(',').any(|g| g == group))
+	}
+}
+
+// The defaults come from the Rocket configuration or the environment, values
+// stored in the database take precedence once they have been loaded.
+fn default_value(rocket: &Rocket<Build>, key: SettingKey) -> Option<String> {
+	let from_config = |path: &str| rocket.figment().extract_inner::<String>(path).ok();
+	let from_env = |name: &str| env::var(name).ok();
+	let cache_time = |name: &str, default: usize| from_env(name).or(Some(default.to_string()));
+	match key {
+		SettingKey::LeaderboardCacheTime => cache_time("LEADERBOARD_CACHE_TIME", 60),
+		SettingKey::LeaderboardSplitsCacheTime => cache_time("LEADERBOARD_SPLITS_CACHE_TIME", 60),
+		SettingKey::LeaderboardLanguagesCacheTime => {
+			cache_time("LEADERBOARD_LANGUAGES_CACHE_TIME", 60)
+		}
+		SettingKey::LeaderboardCommitsCacheTime => {
+			cache_time("LEADERBOARD_COMMITS_CACHE_TIME", 15 * 60)
+		}
+		SettingKey::ProfileCacheTime => cache_time("PROFILE_CACHE_TIME", 60 * 60),
+		SettingKey::OwnerGroups => {
+			from_env("OWNER_GROUPS").or_else(|| from_env("GAMMA_OWNER_GROUP"))
+		}
+		SettingKey::AocSession => from_config("aoc.session").or_else(|| from_env("AOC_SESSION")),
+		SettingKey::GithubClientId => {
+			from_config("github.client_id").or_else(|| from_env("GITHUB_CLIENT_ID"))
+		}
+		SettingKey::GithubClientSecret => {
+			from_config("github.client_secret").or_else(|| from_env("GITHUB_CLIENT_SECRET"))
+		}
+		SettingKey::GithubToken => from_config("github.token").or_else(|| from_env("GITHUB_TOKEN")),
+		SettingKey::SolutionPatterns => from_env("SOLUTION_PATTERNS")
+			.or_else(|| Some("day{day},day_{day},day-{day},{day}".to_owned())),
+		SettingKey::LanguageAllowlist => from_env("LANGUAGE_ALLOWLIST"),
+		SettingKey::LanguageDenylist => from_env("LANGUAGE_DENYLIST")
+			.or_else(|| Some("Shell,Makefile,Dockerfile,HTML,CSS".to_owned())),
+		SettingKey::LanguageMinBytes => from_env("LANGUAGE_MIN_BYTES").or(Some("0".to_owned())),
+		SettingKey::LanguagesPerStar => from_env("LANGUAGES_PER_STAR").or(Some("false".to_owned())),
+		SettingKey::CommitWindow => from_env("COMMIT_WINDOW").or(Some("24".to_owned())),
+	}
+}
+
+pub fn fairing() -> impl Fairing {
+	AdHoc::try_on_ignite("Settings", |rocket| async move {
+		let mut defaults = HashMap::new();
+		for key in SettingKey::ALL {
+			if let Some(value) = default_value(&rocket, key) {
+				match key.normalize(&value) {
+					Some(value) => {
+						defaults.insert(key, value);
+					}
+					None if value.trim().is_empty() => {}
+					None => {
+						eprintln!("Invalid default value \"{}\" for {}.", value, key.as_str());
+						return Err(rocket);
+					}
+				}
+			}
+		}
+
+		Ok(rocket.manage(Settings::new(defaults)))
+	})
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r Settings {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		match request.rocket().state::<Settings>() {
+			Some(settings) => Outcome::Success(settings),
+			None => Outcome::Failure((Status::InternalServerError, ())),
+		}
+	}
+}
@@ -0,0 +1,105 @@
+use diesel::{
+	connection::SimpleConnection,
+	expression_methods::ExpressionMethods,
+	query_dsl::QueryDsl,
+	result::Error,
+	table,
+	Connection,
+	PgConnection,
+	RunQueryDsl,
+};
+
+// The same bookkeeping table as the diesel CLI, so that either may be used.
+table! {
+	__diesel_schema_migrations (version) {
+		version -> VarChar,
+	}
+}
+
+pub struct Migration {
+	pub name: &'static str,
+	up: &'static str,
+	down: &'static str,
+}
+
+impl Migration {
+	pub fn version(&self) -> String {
+		self.name.split('_').next().unwrap().replace('-', "")
+	}
+}
+
+macro_rules! migration {
+	($name:literal) => {
+		Migration {
+			name: $name,
+			up: include_str!(concat!("../../migrations/", $name, "/up.sql")),
+			down: include_str!(concat!("../../migrations/", $name, "/down.sql")),
+		}
+	};
+}
+
+// Oldest first, new migrations are added to the end.
+pub const MIGRATIONS: &[Migration] = &[
+	migration!("2021-11-01-000000_initial"),
+	migration!("2021-11-20-000000_leaderboards"),
+	migration!("2021-11-22-000000_participants_only"),
+	migration!("2021-11-24-000000_scoring"),
+	migration!("2021-11-26-000000_splits_tie_breaker"),
+	migration!("2021-11-28-000000_repos"),
+	migration!("2021-11-30-000000_year_dates"),
+	migration!("2021-12-02-000000_global_roles"),
+];
+
+// Takes a lock on the bookkeeping table, so that only one server at a time
+// changes the schema.
+fn lock(conn: &PgConnection) -> Result<Vec<String>, Error> {
+	conn.batch_execute(
+		"CREATE TABLE IF NOT EXISTS __diesel_schema_migrations (
+			version VARCHAR(50) PRIMARY KEY NOT NULL,
+			run_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+		);
+		LOCK TABLE __diesel_schema_migrations IN EXCLUSIVE MODE;",
+	)?;
+	__diesel_schema_migrations::table
+		.select(__diesel_schema_migrations::columns::version)
+		.order(__diesel_schema_migrations::columns::version)
+		.load(conn)
+}
+
+pub fn applied_migrations(conn: &PgConnection) -> Result<Vec<String>, Error> {
+	conn.transaction(|| lock(conn))
+}
+
+pub fn run_pending_migrations(conn: &PgConnection) -> Result<Vec<&'static Migration>, Error> {
+	conn.transaction(|| {
+		let applied = lock(conn)?;
+		let pending: Vec<_> = MIGRATIONS
+			.iter()
+			.filter(|m| !applied.contains(&m.version()))
+			.collect();
+		for migration in &pending {
+			conn.batch_execute(migration.up)?;
+			diesel::insert_into(__diesel_schema_migrations::table)
+				.values(__diesel_schema_migrations::columns::version.eq(migration.version()))
+				.execute(conn)?;
+		}
+		Ok(pending)
+	})
+}
+
+pub fn revert_latest_migration(conn: &PgConnection) -> Result<Option<&'static Migration>, Error> {
+	conn.transaction(|| {
+		let applied = lock(conn)?;
+		let latest = MIGRATIONS
+			.iter()
+			.rev()
+			.find(|m| applied.contains(&m.version()));
+		if let Some(migration) = latest {
+			conn.batch_execute(migration.down)?;
+			diesel::delete(__diesel_schema_migrations::table)
+				.filter(__diesel_schema_migrations::columns::version.eq(migration.version()))
+				.execute(conn)?;
+		}
+		Ok(latest)
+	})
+}
@@ -1,14 +1,13 @@
-use diesel::{
-	connection::SimpleConnection,
-	table,
-	Associations,
-	Identifiable,
-	Insertable,
-	PgConnection,
-	Queryable,
-};
+// Diesel's derives and macros implement their traits inside of functions.
+#![allow(non_local_definitions)]
+
+pub mod migrations;
+
+use diesel::{table, Associations, Identifiable, Insertable, PgConnection, Queryable};
 use rocket_sync_db_pools::database;
 
+use crate::timestamp;
+
 #[database("sql")]
 pub struct DbConn(PgConnection);
 
@@ -30,21 +29,68 @@ pub struct User {
 table! {
 	years (year) {
 		year -> Integer,
-		leaderboard -> Text,
+		participants_only -> Bool,
+		points -> Text,
+		excluded_days -> Array<Integer>,
+		tie_breaker -> Text,
+		late_joiners -> Bool,
+		splits_tie_breaker -> Text,
+		starts_at -> Nullable<BigInt>,
+		registration_opens -> Nullable<BigInt>,
+		registration_closes -> Nullable<BigInt>,
 	}
 }
 
-#[derive(Identifiable, Insertable, Queryable)]
+#[derive(Identifiable, Queryable)]
 #[primary_key(year)]
 #[table_name = "years"]
 pub struct Year {
 	pub year: i32,
-	pub leaderboard: String,
+	pub participants_only: bool,
+	pub points: String,
+	pub excluded_days: Vec<i32>,
+	pub tie_breaker: String,
+	pub late_joiners: bool,
+	pub splits_tie_breaker: String,
+	pub starts_at: Option<i64>,
+	pub registration_opens: Option<i64>,
+	pub registration_closes: Option<i64>,
 }
 
 impl Year {
-	pub fn leaderboard_id(&self) -> &str {
-		self.leaderboard.split('-').next().unwrap()
+	// When the first puzzle unlocks, unless the year starts some other time.
+	pub fn starts_at(&self) -> i64 {
+		self.starts_at
+			.unwrap_or_else(|| timestamp::puzzle_unlock(self.year, 1))
+	}
+
+	pub fn registration_open(&self, now: i64) -> bool {
+		self.registration_opens.is_none_or(|opens| now >= opens)
+			&& self.registration_closes.is_none_or(|closes| now < closes)
+	}
+}
+
+table! {
+	leaderboards (year, id) {
+		year -> Integer,
+		id -> Text,
+		secret -> Text,
+	}
+}
+
+#[derive(Associations, Identifiable, Insertable, Queryable)]
+#[primary_key(year, id)]
+#[belongs_to(Year, foreign_key = "year")]
+#[table_name = "leaderboards"]
+pub struct YearLeaderboard {
+	pub year: i32,
+	pub id: String,
+	pub secret: String,
+}
+
+impl YearLeaderboard {
+	pub fn code(&self) -> String {
+		format!("{}-{}", self.id, self.secret)
 	}
 }
 
@@ -52,7 +98,8 @@ table! {
 	participants (cid, year) {
 		cid -> Text,
 		year -> Integer,
-		github -> Nullable<Text>,
+		repo -> Nullable<Text>,
+		joined_at -> Nullable<BigInt>,
 	}
 }
 
@@ -64,7 +111,78 @@ table! {
 pub struct Participant {
 	pub cid: String,
 	pub year: i32,
-	pub github: Option<String>,
+	pub repo: Option<String>,
+	pub joined_at: Option<i64>,
+}
+
+table! {
+	tokens (id) {
+		id -> Integer,
+		cid -> Text,
+		name -> Text,
+		token_hash -> Text,
+	}
+}
+
+#[derive(Identifiable, Queryable)]
+#[table_name = "tokens"]
+pub struct Token {
+	pub id: i32,
+	pub cid: String,
+	pub name: String,
+	pub token_hash: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "tokens"]
+pub struct NewToken {
+	pub cid: String,
+	pub name: String,
+	pub token_hash: String,
+}
+
+table! {
+	roles (id) {
+		id -> Integer,
+		subject -> Text,
+		is_group -> Bool,
+		role -> Text,
+		year -> Nullable<Integer>,
+	}
+}
+
+#[derive(Identifiable, Queryable)]
+#[table_name = "roles"]
+pub struct RoleAssignment {
+	pub id: i32,
+	pub subject: String,
+	pub is_group: bool,
+	pub role: String,
+	pub year: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "roles"]
+pub struct NewRoleAssignment {
+	pub subject: String,
+	pub is_group: bool,
+	pub role: String,
+	pub year: Option<i32>,
+}
+
+table! {
+	settings (key) {
+		key -> Text,
+		value -> Text,
+	}
+}
+
+#[derive(Identifiable, Insertable, Queryable)]
+#[primary_key(key)]
+#[table_name = "settings"]
+pub struct Setting {
+	pub key: String,
+	pub value: String,
 }
 
 joinable!(participants -> users (cid));
@@ -74,8 +192,12 @@ pub async fn initialize(rocket: &rocket::Rocket<rocket::Orbit>) -> Result<(), St
 	let conn = DbConn::get_one(rocket)
 		.await
 		.ok_or_else(|| "Database connection not found.".to_string())?;
-	conn.run(|c| c.batch_execute(include_str!("./schema.sql")))
+	let applied = conn
+		.run(|c| migrations::run_pending_migrations(c))
 		.await
-		.map_err(|e| format!("Error creating tables: {}", e))?;
+		.map_err(|e| format!("Error running migrations: {}", e))?;
+	for migration in applied {
+		println!("Applied migration {}", migration.name);
+	}
 	Ok(())
 }
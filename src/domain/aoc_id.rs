@@ -1,33 +1,19 @@
 use diesel::{expression_methods::ExpressionMethods, query_dsl::QueryDsl, RunQueryDsl};
 use rocket::{
 	form::FromForm,
-	http::{CookieJar, Status},
+	http::Status,
 	serde::{Deserialize, Serialize},
 };
 
 use crate::{
 	db::{users, DbConn, User},
-	gamma::GammaClient,
+	identity::IdentityUser,
 };
 
-pub async fn get_aoc_id(
-	conn: &DbConn,
-	cookies: &CookieJar<'_>,
-	gamma_client: &GammaClient,
-) -> Result<AocIdResponse, Status> {
-	let access_cookie = cookies
-		.get(GammaClient::cookie())
-		.ok_or(Status::Unauthorized)?;
-	let user = gamma_client
-		.get_me(access_cookie.value())
-		.await
-		.map_err(|_| Status::Unauthorized)?;
+pub async fn get_aoc_id(conn: &DbConn, user: &IdentityUser) -> Result<AocIdResponse, Status> {
+	let cid = user.cid.clone();
 	let user_db: User = conn
-		.run(move |c| {
-			users::table
-				.filter(users::columns::cid.eq(user.cid))
-				.first(c)
-		})
+		.run(move |c| users::table.filter(users::columns::cid.eq(cid)).first(c))
 		.await
 		.map_err(|err| match err {
 			diesel::result::Error::NotFound => Status::NotFound,
@@ -38,23 +24,12 @@ pub async fn get_aoc_id(
 	})
 }
 
-pub async fn set_aoc_id(
-	aoc_id: String,
-	conn: &DbConn,
-	cookies: &CookieJar<'_>,
-	gamma_client: &GammaClient,
-) -> Result<(), Status> {
-	let access_cookie = cookies
-		.get(GammaClient::cookie())
-		.ok_or(Status::Unauthorized)?;
-	let user = gamma_client
-		.get_me(access_cookie.value())
-		.await
-		.map_err(|_| Status::Unauthorized)?;
+pub async fn set_aoc_id(aoc_id: String, conn: &DbConn, user: &IdentityUser) -> Result<(), Status> {
+	let cid = user.cid.clone();
 	conn.run(move |c| {
 		diesel::insert_into(users::table)
 			.values(User {
-				cid: user.cid,
+				cid,
 				aoc_id: aoc_id.clone(),
 			})
 			.on_conflict(users::columns::cid)
@@ -67,6 +42,24 @@ pub async fn set_aoc_id(
 	Ok(())
 }
 
+// Participations can't exist without an AoC id, so they are removed as well.
+pub async fn delete_aoc_id(conn: &DbConn, user: &IdentityUser) -> Result<(), Status> {
+	let cid = user.cid.clone();
+	let rows_deleted = conn
+		.run(move |c| {
+			diesel::delete(users::table)
+				.filter(users::columns::cid.eq(cid))
+				.execute(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	if rows_deleted == 1 {
+		Ok(())
+	} else {
+		Err(Status::NotFound)
+	}
+}
+
 #[derive(Deserialize, FromForm, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AocIdRequest {
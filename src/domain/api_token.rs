@@ -0,0 +1,172 @@
+use diesel::{
+	expression_methods::ExpressionMethods,
+	query_dsl::QueryDsl,
+	result::DatabaseErrorKind,
+	RunQueryDsl,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use rocket::{
+	form::FromForm,
+	http::Status,
+	serde::{Deserialize, Serialize},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+	auth::AuthUser,
+	db::{tokens, DbConn, NewToken, Token},
+};
+
+const TOKEN_PREFIX: &str = "aod_";
+const TOKEN_LENGTH: usize = 40;
+const TOKEN_NAME_MAX_LENGTH: usize = 64;
+
+fn hash_token(token: &str) -> String {
+	format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub async fn find_api_token_owner(token: String, conn: &DbConn) -> Result<Option<String>, Status> {
+	let token_hash = hash_token(&token);
+	let token_db: Option<Token> = conn
+		.run(move |c| {
+			tokens::table
+				.filter(tokens::columns::token_hash.eq(token_hash))
+				.first(c)
+		})
+		.await
+		.map(Some)
+		.or_else(|err| match err {
+			diesel::result::Error::NotFound => Ok(None),
+			_ => Err(Status::InternalServerError),
+		})?;
+	Ok(token_db.map(|t| t.cid))
+}
+
+pub async fn get_api_tokens(
+	conn: &DbConn,
+	user: &AuthUser,
+) -> Result<Vec<ApiTokenResponse>, Status> {
+	if user.via_token {
+		return Err(Status::Forbidden);
+	}
+
+	let cid = user.cid.clone();
+	let mut tokens_db: Vec<Token> = conn
+		.run(move |c| {
+			tokens::table
+				.filter(tokens::columns::cid.eq(cid))
+				.order(tokens::columns::id)
+				.load(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	Ok(tokens_db
+		.drain(..)
+		.map(|t| ApiTokenResponse {
+			id: t.id,
+			name: t.name,
+		})
+		.collect())
+}
+
+pub async fn create_api_token(
+	data: ApiTokenRequest,
+	conn: &DbConn,
+	user: &AuthUser,
+) -> Result<ApiTokenCreatedResponse, Status> {
+	if user.via_token {
+		return Err(Status::Forbidden);
+	}
+
+	let name = data.name.trim().to_owned();
+	if name.is_empty() || name.len() > TOKEN_NAME_MAX_LENGTH {
+		return Err(Status::BadRequest);
+	}
+
+	let token: String = TOKEN_PREFIX
+		.chars()
+		.chain(
+			rand::thread_rng()
+				.sample_iter(&Alphanumeric)
+				.take(TOKEN_LENGTH)
+				.map(char::from),
+		)
+		.collect();
+	let new_token = NewToken {
+		cid: user.cid.clone(),
+		name: name.clone(),
+		token_hash: hash_token(&token),
+	};
+	let id = conn
+		.run(move |c| {
+			diesel::insert_into(tokens::table)
+				.values(new_token)
+				.returning(tokens::columns::id)
+				.get_result(c)
+		})
+		.await
+		.map_err(|err| match err {
+			diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+				Status::Conflict
+			}
+			_ => Status::InternalServerError,
+		})?;
+	Ok(ApiTokenCreatedResponse {
+		id,
+		name,
+		token,
+	})
+}
+
+pub async fn delete_api_token(
+	data: ApiTokenDeleteRequest,
+	conn: &DbConn,
+	user: &AuthUser,
+) -> Result<(), Status> {
+	if user.via_token {
+		return Err(Status::Forbidden);
+	}
+
+	let cid = user.cid.clone();
+	let rows_deleted = conn
+		.run(move |c| {
+			diesel::delete(tokens::table)
+				.filter(tokens::columns::id.eq(data.id))
+				.filter(tokens::columns::cid.eq(cid))
+				.execute(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	if rows_deleted == 1 {
+		Ok(())
+	} else {
+		Err(Status::NotFound)
+	}
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenRequest {
+	pub name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenResponse {
+	pub id: i32,
+	pub name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenCreatedResponse {
+	pub id: i32,
+	pub name: String,
+	pub token: String,
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenDeleteRequest {
+	pub id: i32,
+}
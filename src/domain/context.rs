@@ -1,11 +1,18 @@
 use rocket::serde::Serialize;
 
 use super::{
+	ApiTokenResponse,
+	LeaderboardCommitsResponse,
 	LeaderboardLanguagesResponse,
 	LeaderboardResponse,
 	LeaderboardSplitsResponse,
-	YearResponse,
+	RoleResponse,
+	Scoring,
+	SettingResponse,
+	YearDates,
+	YearParticipantResponse,
 };
+use crate::{code_host::web_url, identity::IdentityUser};
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -15,24 +22,61 @@ pub struct Context<T: Serialize> {
 	pub data: T,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalLoginContext {
+	pub state: String,
+	pub users: Vec<IdentityUser>,
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SettingsContext {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub aoc_id: Option<String>,
 	#[serde(skip_serializing_if = "Option::is_none")]
-	pub github: Option<String>,
+	pub repo_error: Option<String>,
+	pub years: Vec<ParticipationContext>,
+	pub api_tokens: Vec<ApiTokenResponse>,
 	#[serde(skip_serializing_if = "Option::is_none")]
-	pub year: Option<i32>,
-	pub is_participating: bool,
+	pub admin: Option<AdminContext>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminContext {
+	pub is_owner: bool,
+	pub aoc_session_expired: bool,
+	pub years: Vec<AdminYearContext>,
+	pub roles: Vec<RoleResponse>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigContext {
+	pub settings: Vec<SettingResponse>,
+	pub aoc_session_expired: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParticipationContext {
+	pub year: i32,
 	#[serde(skip_serializing_if = "Option::is_none")]
-	pub owner: Option<OwnerContext>,
+	pub repo: Option<String>,
+	pub is_participating: bool,
+	pub registration_open: bool,
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct OwnerContext {
-	pub years: Vec<YearResponse>,
+pub struct AdminYearContext {
+	pub year: i32,
+	pub leaderboards: Vec<String>,
+	pub scoring: Scoring,
+	pub dates: YearDates,
+	pub can_edit: bool,
+	pub participants: Vec<YearParticipantResponse>,
 }
 
 #[derive(Serialize)]
@@ -43,6 +87,8 @@ pub struct LeaderboardContext {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub join_code: Option<String>,
 	pub value_width: u8,
+	pub profiles_unavailable: bool,
+	pub source_unavailable: bool,
 	pub leaderboard: Vec<LeaderboardPlacementContext>,
 	pub next_update: String,
 }
@@ -61,19 +107,23 @@ impl LeaderboardContext {
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LeaderboardPlacementContext {
+	pub rank: usize,
 	pub nick: String,
 	pub avatar_url: String,
+	pub profile_unavailable: bool,
 	#[serde(skip_serializing_if = "is_none_or_empty")]
-	pub github: Option<String>,
+	pub repo_url: Option<String>,
 	pub value: String,
 }
 
 impl From<LeaderboardResponse> for LeaderboardPlacementContext {
 	fn from(lr: LeaderboardResponse) -> Self {
 		LeaderboardPlacementContext {
+			rank: lr.rank,
 			nick: lr.nick,
 			avatar_url: lr.avatar_url,
-			github: lr.github,
+			profile_unavailable: lr.profile_unavailable,
+			repo_url: lr.repo.as_deref().map(web_url),
 			value: lr.score.to_string(),
 		}
 	}
@@ -82,9 +132,11 @@ impl From<LeaderboardResponse> for LeaderboardPlacementContext {
 impl From<LeaderboardSplitsResponse> for LeaderboardPlacementContext {
 	fn from(lr: LeaderboardSplitsResponse) -> Self {
 		LeaderboardPlacementContext {
+			rank: lr.rank,
 			nick: lr.nick,
 			avatar_url: lr.avatar_url,
-			github: lr.github,
+			profile_unavailable: lr.profile_unavailable,
+			repo_url: lr.repo.as_deref().map(web_url),
 			value: lr.score.to_string(),
 		}
 	}
@@ -93,10 +145,25 @@ impl From<LeaderboardSplitsResponse> for LeaderboardPlacementContext {
 impl From<LeaderboardLanguagesResponse> for LeaderboardPlacementContext {
 	fn from(lr: LeaderboardLanguagesResponse) -> Self {
 		LeaderboardPlacementContext {
+			rank: lr.rank,
+			nick: lr.nick,
+			avatar_url: lr.avatar_url,
+			profile_unavailable: lr.profile_unavailable,
+			repo_url: lr.repo.as_deref().map(web_url),
+			value: lr.score.to_string(),
+		}
+	}
+}
+
+impl From<LeaderboardCommitsResponse> for LeaderboardPlacementContext {
+	fn from(lr: LeaderboardCommitsResponse) -> Self {
+		LeaderboardPlacementContext {
+			rank: lr.rank,
 			nick: lr.nick,
 			avatar_url: lr.avatar_url,
-			github: lr.github,
-			value: lr.languages.len().to_string(),
+			profile_unavailable: lr.profile_unavailable,
+			repo_url: lr.repo.as_deref().map(web_url),
+			value: lr.score.to_string(),
 		}
 	}
 }
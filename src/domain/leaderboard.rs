@@ -1,10 +1,18 @@
-use std::{cmp::Reverse, collections::HashMap};
+use std::{
+	cmp::Reverse,
+	collections::{HashMap, HashSet},
+};
 
-use diesel::{expression_methods::ExpressionMethods, query_dsl::QueryDsl, RunQueryDsl};
-use lazy_static::lazy_static;
+use diesel::{
+	expression_methods::ExpressionMethods,
+	query_dsl::QueryDsl,
+	BelongingToDsl,
+	OptionalExtension,
+	RunQueryDsl,
+};
 use r2d2_redis::redis::Commands;
 use rocket::{
-	http::Status,
+	http::{CookieJar, Status},
 	serde::{
 		json::{serde_json, Json},
 		Deserialize,
@@ -14,30 +22,50 @@ use rocket::{
 };
 use rocket_dyn_templates::Template;
 
+use super::{
+	get_profiles,
+	ranks,
+	solution_languages,
+	solution_paths,
+	LanguageRules,
+	Scoring,
+	SplitsTieBreaker,
+	TieBreaker,
+};
 use crate::{
-	aoc_client::AocClient,
-	db::{participants, users, years, DbConn, Participant, User, Year},
-	gamma::GammaClient,
-	github_client::GitHubClient,
+	aoc::{Leaderboard, LeaderboardSource, Member},
+	code_host::{CodeHostError, CodeHosts},
+	config::Settings,
+	db::{
+		leaderboards,
+		participants,
+		users,
+		years,
+		DbConn,
+		Participant,
+		User,
+		Year,
+		YearLeaderboard,
+	},
+	identity::{self, IdentityProvider},
 	redis::RedisConn,
+	timestamp,
 };
 
-lazy_static! {
-	static ref LEADERBOARD_CACHE_TIME: usize = std::env::var("LEADERBOARD_CACHE_TIME")
-		.map(|s| s.parse().unwrap())
-		.unwrap_or(60);
-	static ref LEADERBOARD_SPLITS_CACHE_TIME: usize = std::env::var("LEADERBOARD_CACHE_TIME")
-		.map(|s| s.parse().unwrap())
-		.unwrap_or(60);
-	static ref LEADERBOARD_LANGUAGES_CACHE_TIME: usize = std::env::var("LEADERBOARD_CACHE_TIME")
-		.map(|s| s.parse().unwrap())
-		.unwrap_or(60);
+// AoC doesn't let more members join a private leaderboard.
+const MAX_LEADERBOARD_MEMBERS: usize = 200;
+
+#[derive(Deserialize, Serialize)]
+struct CachedLeaderboard<L> {
+	leaderboard: L,
+	#[serde(default)]
+	stale: bool,
 }
 
 async fn fetch_from_cache<T: DeserializeOwned>(
 	redis: &RedisConn,
 	key: &str,
-) -> Result<Option<(Vec<T>, usize)>, Status> {
+) -> Result<Option<(Vec<T>, usize, bool)>, Status> {
 	let key_copy = key.to_owned();
 	let cache_result = redis
 		.run(move |c| {
@@ -60,10 +88,11 @@ async fn fetch_from_cache<T: DeserializeOwned>(
 			Status::InternalServerError
 		})?;
 	if let Some((cached, ttl)) = cache_result {
-		if let Ok(cached) = serde_json::from_str::<Vec<T>>(&cached).map_err(|e| {
-			println!("Malformatted redis value: {}", e);
-		}) {
-			return Ok(Some((cached, ttl)));
+		if let Ok(cached) =
+			serde_json::from_str::<CachedLeaderboard<Vec<T>>>(&cached).map_err(|e| {
+				println!("Malformatted redis value: {}", e);
+			}) {
+			return Ok(Some((cached.leaderboard, ttl, cached.stale)));
 		}
 	}
 
@@ -75,8 +104,13 @@ async fn cache_leaderboard<T: Serialize>(
 	key: String,
 	response: &[T],
 	time: usize,
+	stale: bool,
 ) {
-	let cache = serde_json::to_string(response).unwrap();
+	let cache = serde_json::to_string(&CachedLeaderboard {
+		leaderboard: response,
+		stale,
+	})
+	.unwrap();
 	let result = redis
 		.run(move |c| c.set_ex::<_, _, ()>(&key, cache, time))
 		.await;
@@ -86,41 +120,127 @@ async fn cache_leaderboard<T: Serialize>(
 	}
 }
 
-pub async fn get_leaderboard(
-	year: i32,
-	conn: &DbConn,
-	redis: &RedisConn,
-	aoc_client: &AocClient,
-	gamma_client: &GammaClient,
-) -> Result<(Vec<LeaderboardResponse>, usize), Status> {
-	let redis_key = format!("leaderboard_{}", year);
+async fn store_snapshot<T: Serialize>(redis: &RedisConn, key: String, response: &[T]) {
+	let snapshot = serde_json::to_string(response).unwrap();
+	let result = redis.run(move |c| c.set::<_, _, ()>(&key, snapshot)).await;
 
-	if let Some((cached, ttl)) = fetch_from_cache(redis, &redis_key).await? {
-		return Ok((cached, ttl));
+	if let Err(err) = result {
+		println!("Could not store leaderboard snapshot: {:?}", err);
 	}
+}
 
-	let year_db: Year = conn
-		.run(move |c| years::table.filter(years::columns::year.eq(year)).first(c))
-		.await
-		.map_err(|_| Status::NotFound)?;
-
-	let leaderboard = aoc_client
-		.get_leaderboard(year, year_db.leaderboard_id())
+async fn fetch_snapshot<T: DeserializeOwned>(
+	redis: &RedisConn,
+	snapshot_key: String,
+) -> Result<Option<Vec<T>>, Status> {
+	let snapshot = redis
+		.run(move |c| c.get::<_, Option<String>>(snapshot_key))
 		.await
 		.map_err(|e| {
-			println!(
-				"Could not find AoC leaderboard (year {}, id \"{}\") when loading leaderboard \
-				 ({}:{})\n\t{:?}",
-				year,
-				year_db.leaderboard_id(),
-				file!(),
-				line!(),
-				e
-			);
+			println!("Redis error: {}", e);
 			Status::InternalServerError
 		})?;
+	Ok(snapshot.and_then(|snapshot| {
+		serde_json::from_str(&snapshot)
+			.map_err(|e| {
+				println!("Malformatted redis value: {}", e);
+			})
+			.ok()
+	}))
+}
 
-	let mut participants: Vec<(Participant, User)> = conn
+async fn from_snapshot<T: DeserializeOwned + Serialize>(
+	redis: &RedisConn,
+	key: String,
+	snapshot_key: String,
+	time: usize,
+) -> Result<(Vec<T>, usize, bool), Status> {
+	let snapshot: Vec<T> = fetch_snapshot(redis, snapshot_key)
+		.await?
+		.ok_or(Status::ServiceUnavailable)?;
+
+	cache_leaderboard(redis, key, &snapshot, time, true).await;
+
+	Ok((snapshot, time, true))
+}
+
+// Entries of repos that can't be read until the rate limit of their host is
+// renewed are paired with false. They keep what they had on the last snapshot,
+// or are left out if they weren't on it.
+async fn fill_from_snapshot<T: DeserializeOwned>(
+	redis: &RedisConn,
+	snapshot_key: String,
+	entries: Vec<(T, bool)>,
+	cid: impl Fn(&T) -> &str,
+	fill: impl Fn(&mut T, T),
+) -> Result<Vec<T>, Status> {
+	if entries.iter().all(|(_, fresh)| *fresh) {
+		return Ok(entries.into_iter().map(|(entry, _)| entry).collect());
+	}
+	let mut last_known: HashMap<String, T> = fetch_snapshot(redis, snapshot_key)
+		.await?
+		.unwrap_or_default()
+		.into_iter()
+		.map(|entry| (cid(&entry).to_owned(), entry))
+		.collect();
+	Ok(entries
+		.into_iter()
+		.filter_map(|(mut entry, fresh)| {
+			if !fresh {
+				let last = last_known.remove(cid(&entry))?;
+				fill(&mut entry, last);
+			}
+			Some(entry)
+		})
+		.collect())
+}
+
+// Fetches every private leaderboard of the year and merges them, scored by
+// the rules of the year, along with its participants. Gives None if any of
+// them can't be fetched. Which AoC members are on which leaderboard is
+// remembered, to hand out join codes.
+async fn fetch_leaderboard(
+	year: i32,
+	conn: &DbConn,
+	redis: &RedisConn,
+	leaderboard_source: &dyn LeaderboardSource,
+) -> Result<Option<(Scoring, Leaderboard, Vec<(Participant, User)>)>, Status> {
+	let (year_db, boards) = get_year_leaderboards(year, conn).await?;
+
+	let mut leaderboards = Vec::new();
+	let mut board_members = HashMap::new();
+	for board in &boards {
+		match leaderboard_source.get_leaderboard(year, &board.id).await {
+			Ok(leaderboard) => {
+				board_members.insert(
+					board.id.to_owned(),
+					leaderboard.members.keys().cloned().collect::<Vec<_>>(),
+				);
+				leaderboards.push(leaderboard);
+			}
+			Err(e) => {
+				println!(
+					"Could not find AoC leaderboard (year {}, id \"{}\") when loading \
+					 leaderboard, falling back to the last snapshot ({}:{})\n\t{:?}",
+					year,
+					board.id,
+					file!(),
+					line!(),
+					e
+				);
+				return Ok(None);
+			}
+		}
+	}
+
+	let members = serde_json::to_string(&board_members).unwrap();
+	let key = format!("leaderboard_boards_{}", year);
+	let result = redis.run(move |c| c.set::<_, _, ()>(&key, members)).await;
+	if let Err(err) = result {
+		println!("Could not store leaderboard members: {:?}", err);
+	}
+
+	let participants: Vec<(Participant, User)> = conn
 		.run(move |c| {
 			participants::table
 				.inner_join(users::table)
@@ -138,250 +258,599 @@ pub async fn get_leaderboard(
 			Status::InternalServerError
 		})?;
 
-	let mut response: Vec<Result<_, ()>> = futures::future::join_all(
-		participants
-			.drain(..)
-			.filter_map(|(p, u)| {
-				leaderboard
-					.members
-					.get(&u.aoc_id)
-					.map(|m| LeaderboardResponse {
-						cid: u.cid.clone(),
-						nick: String::new(),
-						avatar_url: String::new(),
-						github: p.github,
-						score: m.local_score,
-					})
-			})
-			.map(async move |mut lr| {
-				let user = gamma_client.get_user(&lr.cid).await.map_err(|e| {
-					println!(
-						"Could not get user {} when loading leaderboard ({}:{})\n\t{:?}",
-						lr.cid,
-						file!(),
-						line!(),
-						e
-					);
-				})?;
-				lr.nick.push_str(&user.nick);
-				lr.avatar_url.push_str(&user.avatar_url);
-				Ok(lr)
-			}),
-	)
-	.await;
-	let mut response: Vec<_> = response.drain(..).filter_map(|r| r.ok()).collect();
-	response.sort_by_key(|lr| Reverse(lr.score));
+	let scoring = Scoring::from(&year_db);
+	let merged = leaderboards.len() > 1;
+	let mut leaderboard = Leaderboard::merge(leaderboards);
+	if scoring.participants_only {
+		// AoC members who never signed up here don't take any points.
+		let registered: HashSet<_> = participants.iter().map(|(_, u)| &u.aoc_id).collect();
+		leaderboard.members.retain(|id, _| registered.contains(id));
+	}
+	if merged || !scoring.is_default() {
+		let joined_at = participants
+			.iter()
+			.map(|(p, u)| (u.aoc_id.to_owned(), p.joined_at))
+			.collect();
+		scoring.rescore(&mut leaderboard, &joined_at);
+	}
 
-	cache_leaderboard(redis, redis_key, &response, *LEADERBOARD_CACHE_TIME).await;
+	Ok(Some((scoring, leaderboard, participants)))
+}
 
-	Ok((response, *LEADERBOARD_CACHE_TIME))
+async fn get_year_leaderboards(
+	year: i32,
+	conn: &DbConn,
+) -> Result<(Year, Vec<YearLeaderboard>), Status> {
+	conn.run(move |c| {
+		let year_db: Year = years::table
+			.filter(years::columns::year.eq(year))
+			.first(c)?;
+		let boards = YearLeaderboard::belonging_to(&year_db)
+			.order(leaderboards::columns::id)
+			.load(c)?;
+		Ok((year_db, boards))
+	})
+	.await
+	.map_err(|e| match e {
+		diesel::result::Error::NotFound => Status::NotFound,
+		e => {
+			println!(
+				"Could not fetch from database when loading leaderboard ({}:{})\n\t{:?}",
+				file!(),
+				line!(),
+				e
+			);
+			Status::InternalServerError
+		}
+	})
 }
 
-pub async fn get_leaderboard_splits(
+// Signed in participants are shown the code of the leaderboard they are on,
+// anyone else the code of the first leaderboard with room left. Until the
+// leaderboards have been fetched they are all assumed to have room.
+pub async fn get_join_code(
 	year: i32,
 	conn: &DbConn,
 	redis: &RedisConn,
-	aoc_client: &AocClient,
-	gamma_client: &GammaClient,
-) -> Result<(Vec<LeaderboardSplitsResponse>, usize), Status> {
-	let redis_key = format!("leaderboard_splits_{}", year);
-
-	if let Some((cached, ttl)) = fetch_from_cache(redis, &redis_key).await? {
-		return Ok((cached, ttl));
-	}
-
-	let year_db: Year = conn
-		.run(move |c| years::table.filter(years::columns::year.eq(year)).first(c))
+	cookies: &CookieJar<'_>,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<Option<String>, Status> {
+	let access_cookie = cookies
+		.get(identity::cookie())
+		.ok_or(Status::Unauthorized)?;
+	let user = identity_provider
+		.get_me(access_cookie.value())
 		.await
-		.map_err(|_| Status::NotFound)?;
+		.map_err(|_| Status::Unauthorized)?;
 
-	let leaderboard = aoc_client
-		.get_leaderboard(year, year_db.leaderboard_id())
+	let (_, boards) = get_year_leaderboards(year, conn).await?;
+	let aoc_id: Option<String> = conn
+		.run(move |c| {
+			users::table
+				.filter(users::columns::cid.eq(user.cid))
+				.select(users::columns::aoc_id)
+				.first(c)
+				.optional()
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	let key = format!("leaderboard_boards_{}", year);
+	let board_members: HashMap<String, Vec<String>> = redis
+		.run(move |c| c.get::<_, Option<String>>(key))
 		.await
 		.map_err(|e| {
-			println!(
-				"Could not find AoC leaderboard (year {}, id \"{}\") when loading leaderboard \
-				 ({}:{})\n\t{:?}",
-				year,
-				year_db.leaderboard_id(),
-				file!(),
-				line!(),
-				e
-			);
+			println!("Redis error: {}", e);
 			Status::InternalServerError
-		})?;
+		})?
+		.and_then(|members| serde_json::from_str(&members).ok())
+		.unwrap_or_default();
+
+	let members =
+		|board: &YearLeaderboard| board_members.get(&board.id).map_or(&[][..], Vec::as_slice);
+	Ok(boards
+		.iter()
+		.find(|board| {
+			aoc_id
+				.as_ref()
+				.is_some_and(|id| members(board).contains(id))
+		})
+		.or_else(|| {
+			boards
+				.iter()
+				.find(|board| members(board).len() < MAX_LEADERBOARD_MEMBERS)
+		})
+		.map(YearLeaderboard::code))
+}
 
-	let participants: Vec<(Participant, User)> = conn
+// Snapshots are kept, they are only replaced by newer leaderboards.
+pub async fn purge_leaderboards(redis: &RedisConn, year: Option<i32>) -> Result<usize, Status> {
+	redis
 		.run(move |c| {
-			participants::table
-				.inner_join(users::table)
-				.filter(participants::columns::year.eq(year))
-				.load(c)
+			let keys: Vec<String> = match year {
+				Some(year) => vec![
+					format!("leaderboard_{}", year),
+					format!("leaderboard_splits_{}", year),
+					format!("leaderboard_languages_{}", year),
+					format!("leaderboard_commits_{}", year),
+				],
+				None => c
+					.scan_match::<_, String>("leaderboard_*")?
+					.filter(|key| !key.contains("_last_"))
+					.collect(),
+			};
+			if keys.is_empty() {
+				return Ok(0);
+			}
+			c.del(keys)
 		})
 		.await
 		.map_err(|e| {
-			println!(
-				"Could not fetch from database when loading leaderboard ({}:{})\n\t{:?}",
-				file!(),
-				line!(),
-				e
-			);
+			println!("Redis error: {}", e);
 			Status::InternalServerError
-		})?;
+		})
+}
+
+pub async fn get_leaderboard(
+	year: i32,
+	conn: &DbConn,
+	redis: &RedisConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<(Vec<LeaderboardResponse>, usize, bool), Status> {
+	let cache_time = settings.leaderboard_cache_time();
+	let redis_key = format!("leaderboard_{}", year);
+	let snapshot_key = format!("leaderboard_last_{}", year);
+
+	if let Some(cached) = fetch_from_cache(redis, &redis_key).await? {
+		return Ok(cached);
+	}
+
+	let (scoring, leaderboard, mut participants) =
+		match fetch_leaderboard(year, conn, redis, leaderboard_source).await? {
+			Some(leaderboard) => leaderboard,
+			None => return from_snapshot(redis, redis_key, snapshot_key, cache_time).await,
+		};
+
+	let mut members: Vec<_> = participants
+		.drain(..)
+		.filter_map(|(p, u)| leaderboard.members.get(&u.aoc_id).map(|m| (m, p, u)))
+		.collect();
+	// Ties are broken by the rules of the year, or else share a place.
+	let tie_key = |m: &Member| match scoring.tie_breaker {
+		TieBreaker::None => (Reverse(m.local_score), 0),
+		TieBreaker::LastStar => (Reverse(m.local_score), m.last_star_ts().unwrap_or(u64::MAX)),
+	};
+	members.sort_by(|(a, _, ua), (b, _, ub)| {
+		tie_key(a)
+			.cmp(&tie_key(b))
+			.then_with(|| ua.cid.cmp(&ub.cid))
+	});
+	let member_ranks = ranks(members.iter().map(|(m, ..)| tie_key(m)));
+
+	let profiles = get_profiles(
+		members.iter().map(|(_, _, u)| u.cid.to_owned()).collect(),
+		redis,
+		settings,
+		identity_provider,
+	)
+	.await;
+
+	let response: Vec<_> = members
+		.into_iter()
+		.zip(member_ranks)
+		.map(|((m, p, u), rank)| {
+			let profile = &profiles[&u.cid];
+			LeaderboardResponse {
+				rank,
+				nick: profile.nick.to_owned(),
+				avatar_url: profile.avatar_url.to_owned(),
+				profile_unavailable: profile.unavailable,
+				cid: u.cid,
+				repo: p.repo,
+				score: m.local_score,
+			}
+		})
+		.collect();
+
+	cache_leaderboard(redis, redis_key, &response, cache_time, false).await;
+	store_snapshot(redis, snapshot_key, &response).await;
+
+	Ok((response, cache_time, false))
+}
+
+pub async fn get_leaderboard_splits(
+	year: i32,
+	conn: &DbConn,
+	redis: &RedisConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<(Vec<LeaderboardSplitsResponse>, usize, bool), Status> {
+	let cache_time = settings.leaderboard_splits_cache_time();
+	let redis_key = format!("leaderboard_splits_{}", year);
+	let snapshot_key = format!("leaderboard_splits_last_{}", year);
+
+	if let Some(cached) = fetch_from_cache(redis, &redis_key).await? {
+		return Ok(cached);
+	}
+
+	let (scoring, leaderboard, participants) =
+		match fetch_leaderboard(year, conn, redis, leaderboard_source).await? {
+			Some(leaderboard) => leaderboard,
+			None => return from_snapshot(redis, redis_key, snapshot_key, cache_time).await,
+		};
 
 	let unprocessed_members: Vec<_> = participants
 		.into_iter()
 		.filter_map(|(p, u)| leaderboard.members.get(&u.aoc_id).map(|m| (m, p, u)))
 		.collect();
 
-	let mut members: Vec<Result<_, ()>> = futures::future::join_all(
+	let profiles = get_profiles(
 		unprocessed_members
 			.iter()
-			.map(|(_, p, u)| LeaderboardSplitsResponse {
+			.map(|(_, _, u)| u.cid.to_owned())
+			.collect(),
+		redis,
+		settings,
+		identity_provider,
+	)
+	.await;
+
+	let mut members: HashMap<_, _> = unprocessed_members
+		.iter()
+		.map(|(_, p, u)| {
+			let profile = &profiles[&u.cid];
+			(u.cid.to_owned(), LeaderboardSplitsResponse {
+				rank: 0,
 				cid: u.cid.to_owned(),
-				nick: String::new(),
-				avatar_url: String::new(),
-				github: p.github.to_owned(),
+				nick: profile.nick.to_owned(),
+				avatar_url: profile.avatar_url.to_owned(),
+				profile_unavailable: profile.unavailable,
+				repo: p.repo.to_owned(),
 				score: 0,
 			})
-			.map(async move |mut lr| {
-				let user = gamma_client.get_user(&lr.cid).await.map_err(|e| {
-					println!(
-						"Could not get user {} when loading leaderboard ({}:{})\n\t{:?}",
-						lr.cid,
-						file!(),
-						line!(),
-						e
-					);
-				})?;
-				lr.nick.push_str(&user.nick);
-				lr.avatar_url.push_str(&user.avatar_url);
-				Ok(lr)
-			}),
-	)
-	.await;
-	let mut members: HashMap<_, _> = members
-		.drain(..)
-		.filter_map(|r| r.ok().map(|m| (m.cid.to_owned(), m)))
+		})
 		.collect();
 
 	let total_members = members.len() as u16;
+	let mut split_times = HashMap::new();
 	let mut day_vec = Vec::new();
 	for day in 1..=25 {
 		let day_str = day.to_string();
 
-		day_vec.extend(unprocessed_members.iter().filter_map(|(m, _, u)| {
-			m.completion_day_level
-				.get(&day_str)
-				.map(|d| match (d.first_star_ts, d.second_star_ts) {
-					(Some(f), Some(s)) => Some((&u.cid, s - f)),
+		day_vec.extend(unprocessed_members.iter().filter_map(|(m, p, u)| {
+			m.completion_day_level.get(&day_str).and_then(|d| {
+				match (d.first_star_ts, d.second_star_ts) {
+					(Some(f), Some(s)) if scoring.counts(day, f, p.joined_at) => {
+						Some((&u.cid, s - f))
+					}
 					_ => None,
-				})
-				.flatten()
+				}
+			})
 		}));
 		day_vec.sort_by_key(|&(_, split)| split);
 
-		for (i, (cid, _)) in day_vec.iter().enumerate() {
+		for (i, (cid, split)) in day_vec.iter().enumerate() {
 			if let Some(m) = members.get_mut(*cid) {
 				m.score += total_members - i as u16;
 			}
+			*split_times.entry(*cid).or_insert(0) += split;
 		}
 
 		day_vec.clear();
 	}
 
 	let mut response: Vec<_> = members.drain().map(|(_, v)| v).collect();
-	response.sort_by_key(|lr| Reverse(lr.score));
+	let tie_key = |lr: &LeaderboardSplitsResponse| match scoring.splits_tie_breaker {
+		SplitsTieBreaker::None => (Reverse(lr.score), 0),
+		SplitsTieBreaker::TotalTime => (
+			Reverse(lr.score),
+			split_times.get(&lr.cid).copied().unwrap_or(0),
+		),
+	};
+	response.sort_by(|a, b| tie_key(a).cmp(&tie_key(b)).then_with(|| a.cid.cmp(&b.cid)));
+	let response_ranks = ranks(response.iter().map(tie_key));
+	for (lr, rank) in response.iter_mut().zip(response_ranks) {
+		lr.rank = rank;
+	}
 
-	cache_leaderboard(redis, redis_key, &response, *LEADERBOARD_SPLITS_CACHE_TIME).await;
+	cache_leaderboard(redis, redis_key, &response, cache_time, false).await;
+	store_snapshot(redis, snapshot_key, &response).await;
 
-	Ok((response, *LEADERBOARD_SPLITS_CACHE_TIME))
+	Ok((response, cache_time, false))
 }
 
+// Languages only count for the days they were used to solve, as found by
+// the solution patterns in the repos, and if they pass the language rules.
 pub async fn get_leaderboard_languages(
 	year: i32,
 	conn: &DbConn,
 	redis: &RedisConn,
-	gamma_client: &GammaClient,
-	github_client: &GitHubClient,
-) -> Result<(Vec<LeaderboardLanguagesResponse>, usize), Status> {
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+	code_hosts: &CodeHosts,
+) -> Result<(Vec<LeaderboardLanguagesResponse>, usize, bool), Status> {
+	let cache_time = settings.leaderboard_languages_cache_time();
 	let redis_key = format!("leaderboard_languages_{}", year);
+	let snapshot_key = format!("leaderboard_languages_last_{}", year);
 
-	if let Some((cached, ttl)) = fetch_from_cache(redis, &redis_key).await? {
-		return Ok((cached, ttl));
+	if let Some(cached) = fetch_from_cache(redis, &redis_key).await? {
+		return Ok(cached);
 	}
 
-	let mut participants: Vec<(Participant, User)> = conn
-		.run(move |c| {
-			participants::table
-				.inner_join(users::table)
-				.filter(participants::columns::year.eq(year))
-				.filter(participants::columns::github.is_not_null())
-				.filter(participants::columns::github.ne(""))
-				.load(c)
-		})
-		.await
-		.map_err(|e| {
-			println!(
-				"Could not fetch from database when loading leaderboard ({}:{})\n\t{:?}",
-				file!(),
-				line!(),
-				e
-			);
-			Status::InternalServerError
-		})?;
+	let (scoring, leaderboard, participants) =
+		match fetch_leaderboard(year, conn, redis, leaderboard_source).await? {
+			Some(leaderboard) => leaderboard,
+			None => return from_snapshot(redis, redis_key, snapshot_key, cache_time).await,
+		};
+
+	let members: Vec<_> = participants
+		.into_iter()
+		.filter(|(p, _)| p.repo.as_ref().is_some_and(|repo| !repo.is_empty()))
+		.filter_map(|(p, u)| leaderboard.members.get(&u.aoc_id).map(|m| (m, p, u)))
+		.collect();
+
+	let profiles = get_profiles(
+		members.iter().map(|(_, _, u)| u.cid.to_owned()).collect(),
+		redis,
+		settings,
+		identity_provider,
+	)
+	.await;
 
+	let profiles = &profiles;
+	let scoring = &scoring;
+	let patterns = &settings.solution_patterns();
+	let rules = &LanguageRules::from(settings);
+	let per_star = settings.languages_per_star();
 	let mut response: Vec<Result<_, ()>> =
-		futures::future::join_all(participants.drain(..).map(async move |(p, u)| {
-			let user = gamma_client.get_user(&u.cid).await.map_err(|e| {
-				println!(
-					"Could not get user {} when loading leaderboard ({}:{})\n\t{:?}",
-					u.cid,
-					file!(),
-					line!(),
-					e
-				);
-			})?;
-			let github = p.github.as_ref().unwrap();
-			let languages = github_client.get_languages(github).await.map_err(|e| {
-				println!(
-					"Could not get repo {:?} when loading leaderboard ({}:{})\n\t{:?}",
-					github,
-					file!(),
-					line!(),
-					e
-				);
-			})?;
-			Ok(LeaderboardLanguagesResponse {
+		futures::future::join_all(members.into_iter().map(move |(m, p, u)| async move {
+			let profile = &profiles[&u.cid];
+			let repo = p.repo.as_ref().unwrap();
+			let mut entry = LeaderboardLanguagesResponse {
+				rank: 0,
 				cid: u.cid.clone(),
-				nick: user.nick,
-				avatar_url: user.avatar_url,
-				github: p.github,
-				languages: languages.into_keys().collect(),
-			})
+				nick: profile.nick.to_owned(),
+				avatar_url: profile.avatar_url.to_owned(),
+				profile_unavailable: profile.unavailable,
+				repo: p.repo.clone(),
+				score: 0,
+				languages: Vec::new(),
+			};
+			let tree = match code_hosts.get_tree(repo).await {
+				Ok(tree) => tree,
+				// Filled in from the snapshot below.
+				Err(CodeHostError::RateLimited) => return Ok((entry, false)),
+				Err(e) => {
+					println!(
+						"Could not get repo {:?} when loading leaderboard ({}:{})\n\t{:?}",
+						repo,
+						file!(),
+						line!(),
+						e
+					);
+					return Err(());
+				}
+			};
+			let mut score = 0;
+			let mut languages = Vec::new();
+			for (day, day_languages) in solution_languages(&tree, patterns) {
+				let stars = m.completion_day_level.get(&day.to_string()).map_or(0, |d| {
+					[d.first_star_ts, d.second_star_ts]
+						.iter()
+						.flatten()
+						.filter(|&&ts| scoring.counts(day, ts, p.joined_at))
+						.count()
+				});
+				if stars == 0 {
+					continue;
+				}
+				let day_languages: Vec<_> = day_languages
+					.into_iter()
+					.filter(|&(language, bytes)| rules.counts(language, bytes))
+					.map(|(language, _)| language)
+					.collect();
+				if per_star {
+					score += stars * day_languages.len();
+				}
+				languages.extend(day_languages);
+			}
+			languages.sort_unstable();
+			languages.dedup();
+			if !per_star {
+				score = languages.len();
+			}
+			entry.score = score;
+			entry.languages = languages.into_iter().map(str::to_owned).collect();
+			Ok((entry, true))
 		}))
 		.await;
-	let mut response: Vec<_> = response.drain(..).filter_map(|r| r.ok()).collect();
-	response.sort_by_key(|lr| Reverse(lr.languages.len()));
+	let response: Vec<_> = response.drain(..).filter_map(|r| r.ok()).collect();
+	let mut response = fill_from_snapshot(
+		redis,
+		snapshot_key.clone(),
+		response,
+		|lr| lr.cid.as_str(),
+		|lr, last| {
+			lr.score = last.score;
+			lr.languages = last.languages;
+		},
+	)
+	.await?;
+	response.sort_by(|a, b| {
+		Reverse(a.score)
+			.cmp(&Reverse(b.score))
+			.then_with(|| a.cid.cmp(&b.cid))
+	});
+	let response_ranks = ranks(response.iter().map(|lr| lr.score));
+	for (lr, rank) in response.iter_mut().zip(response_ranks) {
+		lr.rank = rank;
+	}
+
+	cache_leaderboard(redis, redis_key, &response, cache_time, false).await;
+	store_snapshot(redis, snapshot_key, &response).await;
+
+	Ok((response, cache_time, false))
+}
+
+// A day counts if the participant has a star from it that counts, their repo
+// has a solution for it, and a commit that changes the solution was made within
+// the commit window after the puzzle unlocked.
+pub async fn get_leaderboard_commits(
+	year: i32,
+	conn: &DbConn,
+	redis: &RedisConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+	code_hosts: &CodeHosts,
+) -> Result<(Vec<LeaderboardCommitsResponse>, usize, bool), Status> {
+	let cache_time = settings.leaderboard_commits_cache_time();
+	let redis_key = format!("leaderboard_commits_{}", year);
+	let snapshot_key = format!("leaderboard_commits_last_{}", year);
+
+	if let Some(cached) = fetch_from_cache(redis, &redis_key).await? {
+		return Ok(cached);
+	}
+
+	let (scoring, leaderboard, participants) =
+		match fetch_leaderboard(year, conn, redis, leaderboard_source).await? {
+			Some(leaderboard) => leaderboard,
+			None => return from_snapshot(redis, redis_key, snapshot_key, cache_time).await,
+		};
+
+	let members: Vec<_> = participants
+		.into_iter()
+		.filter(|(p, _)| p.repo.as_ref().is_some_and(|repo| !repo.is_empty()))
+		.filter_map(|(p, u)| leaderboard.members.get(&u.aoc_id).map(|m| (m, p, u)))
+		.collect();
 
-	cache_leaderboard(
+	let profiles = get_profiles(
+		members.iter().map(|(_, _, u)| u.cid.to_owned()).collect(),
 		redis,
-		redis_key,
-		&response,
-		*LEADERBOARD_LANGUAGES_CACHE_TIME,
+		settings,
+		identity_provider,
 	)
 	.await;
 
-	Ok((response, *LEADERBOARD_LANGUAGES_CACHE_TIME))
+	let profiles = &profiles;
+	let scoring = &scoring;
+	let patterns = &settings.solution_patterns();
+	let window = settings.commit_window();
+	let mut response: Vec<Result<_, ()>> =
+		futures::future::join_all(members.into_iter().map(move |(m, p, u)| async move {
+			let profile = &profiles[&u.cid];
+			let repo = p.repo.as_ref().unwrap();
+			let mut entry = LeaderboardCommitsResponse {
+				rank: 0,
+				cid: u.cid.clone(),
+				nick: profile.nick.to_owned(),
+				avatar_url: profile.avatar_url.to_owned(),
+				profile_unavailable: profile.unavailable,
+				repo: p.repo.clone(),
+				score: 0,
+				days: Vec::new(),
+			};
+			// The code host is asked about the commits to each path that holds
+			// the solution of a solved day, until one of them was changed in
+			// time.
+			let days = async {
+				let tree = code_hosts.get_tree(repo).await?;
+				let mut days = Vec::new();
+				for (day, paths) in solution_paths(&tree, patterns) {
+					let solved = m
+						.completion_day_level
+						.get(&day.to_string())
+						.is_some_and(|d| {
+							[d.first_star_ts, d.second_star_ts]
+								.iter()
+								.flatten()
+								.any(|&ts| scoring.counts(day, ts, p.joined_at))
+						});
+					if !solved {
+						continue;
+					}
+					let unlock = timestamp::puzzle_unlock(year, day);
+					for path in paths {
+						let commit_times = code_hosts
+							.get_commit_times(repo, path, unlock, unlock + window)
+							.await?;
+						if commit_times
+							.iter()
+							.any(|&time| (unlock..unlock + window).contains(&time))
+						{
+							days.push(day);
+							break;
+						}
+					}
+				}
+				Ok::<_, CodeHostError>(days)
+			}
+			.await;
+			let mut days = match days {
+				Ok(days) => days,
+				// Filled in from the snapshot below.
+				Err(CodeHostError::RateLimited) => return Ok((entry, false)),
+				Err(e) => {
+					println!(
+						"Could not get repo {:?} when loading leaderboard ({}:{})\n\t{:?}",
+						repo,
+						file!(),
+						line!(),
+						e
+					);
+					return Err(());
+				}
+			};
+			days.sort_unstable();
+			entry.score = days.len();
+			entry.days = days;
+			Ok((entry, true))
+		}))
+		.await;
+	let response: Vec<_> = response.drain(..).filter_map(|r| r.ok()).collect();
+	let mut response = fill_from_snapshot(
+		redis,
+		snapshot_key.clone(),
+		response,
+		|lr| lr.cid.as_str(),
+		|lr, last| {
+			lr.score = last.score;
+			lr.days = last.days;
+		},
+	)
+	.await?;
+	response.sort_by(|a, b| {
+		Reverse(a.score)
+			.cmp(&Reverse(b.score))
+			.then_with(|| a.cid.cmp(&b.cid))
+	});
+	let response_ranks = ranks(response.iter().map(|lr| lr.score));
+	for (lr, rank) in response.iter_mut().zip(response_ranks) {
+		lr.rank = rank;
+	}
+
+	cache_leaderboard(redis, redis_key, &response, cache_time, false).await;
+	store_snapshot(redis, snapshot_key, &response).await;
+
+	Ok((response, cache_time, false))
 }
 
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LeaderboardResponse {
+	// Competition ranking, entries that tie share a place.
+	pub rank: usize,
 	pub cid: String,
 	pub nick: String,
 	pub avatar_url: String,
-	pub github: Option<String>,
+	#[serde(default)]
+	pub profile_unavailable: bool,
+	// Repos used to be GitHub only, the API still calls them that.
+	#[serde(rename = "github")]
+	pub repo: Option<String>,
 	pub score: u16,
 }
 
@@ -400,19 +869,51 @@ impl JsonOrTemplateLeaderboard {
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LeaderboardSplitsResponse {
+	// Competition ranking, entries that tie share a place.
+	pub rank: usize,
 	pub cid: String,
 	pub nick: String,
 	pub avatar_url: String,
-	pub github: Option<String>,
+	#[serde(default)]
+	pub profile_unavailable: bool,
+	// Repos used to be GitHub only, the API still calls them that.
+	#[serde(rename = "github")]
+	pub repo: Option<String>,
 	pub score: u16,
 }
 
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LeaderboardLanguagesResponse {
+	// Competition ranking, entries that tie share a place.
+	pub rank: usize,
 	pub cid: String,
 	pub nick: String,
 	pub avatar_url: String,
-	pub github: Option<String>,
+	#[serde(default)]
+	pub profile_unavailable: bool,
+	// Repos used to be GitHub only, the API still calls them that.
+	#[serde(rename = "github")]
+	pub repo: Option<String>,
+	// The number of languages, or with languages per star the number of
+	// languages of every star.
+	pub score: usize,
 	pub languages: Vec<String>,
 }
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardCommitsResponse {
+	// Competition ranking, entries that tie share a place.
+	pub rank: usize,
+	pub cid: String,
+	pub nick: String,
+	pub avatar_url: String,
+	#[serde(default)]
+	pub profile_unavailable: bool,
+	#[serde(rename = "github")]
+	pub repo: Option<String>,
+	// The number of days with a commit in time.
+	pub score: usize,
+	pub days: Vec<u8>,
+}
@@ -1,11 +1,23 @@
 mod aoc_id;
+mod api_token;
 mod context;
 mod leaderboard;
 mod participate;
+mod profile;
+mod role;
+mod scoring;
+mod setting;
+mod solutions;
 mod year;
 
 pub use aoc_id::*;
+pub use api_token::*;
 pub use context::*;
 pub use leaderboard::*;
 pub use participate::*;
+pub use profile::*;
+pub use role::*;
+pub use scoring::*;
+pub use setting::*;
+pub use solutions::*;
 pub use year::*;
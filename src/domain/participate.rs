@@ -1,31 +1,35 @@
-use diesel::{expression_methods::ExpressionMethods, query_dsl::QueryDsl, RunQueryDsl};
+use diesel::{
+	expression_methods::ExpressionMethods,
+	query_dsl::QueryDsl,
+	Connection,
+	OptionalExtension,
+	PgConnection,
+	RunQueryDsl,
+};
 use rocket::{
 	form::FromForm,
-	http::{CookieJar, Status},
+	http::Status,
 	serde::{Deserialize, Serialize},
 };
 
+use super::Role;
 use crate::{
-	db::{participants, DbConn, Participant},
-	gamma::GammaClient,
+	auth::Authorized,
+	code_host::{CodeHost, CodeHosts},
+	db::{participants, years, DbConn, Participant, Year},
+	identity::IdentityUser,
+	timestamp,
 };
 
 pub async fn get_participations(
 	conn: &DbConn,
-	cookies: &CookieJar<'_>,
-	gamma_client: &GammaClient,
+	user: &IdentityUser,
 ) -> Result<Vec<ParticipateResponse>, Status> {
-	let access_cookie = cookies
-		.get(GammaClient::cookie())
-		.ok_or(Status::Unauthorized)?;
-	let user = gamma_client
-		.get_me(access_cookie.value())
-		.await
-		.map_err(|_| Status::Unauthorized)?;
+	let cid = user.cid.clone();
 	let mut participant: Vec<Participant> = conn
 		.run(move |c| {
 			participants::table
-				.filter(participants::columns::cid.eq(user.cid))
+				.filter(participants::columns::cid.eq(cid))
 				.load(c)
 		})
 		.await
@@ -37,58 +41,190 @@ pub async fn get_participations(
 		.drain(..)
 		.map(|p| ParticipateResponse {
 			year: p.year,
-			github: p.github,
+			repo: p.repo,
 		})
 		.collect())
 }
 
 pub async fn set_participation(
-	data: ParticipateRequest,
+	mut data: ParticipateRequest,
 	conn: &DbConn,
-	cookies: &CookieJar<'_>,
-	gamma_client: &GammaClient,
-) -> Result<(), Status> {
-	let access_cookie = cookies
-		.get(GammaClient::cookie())
-		.ok_or(Status::Unauthorized)?;
-	let user = gamma_client
-		.get_me(access_cookie.value())
-		.await
-		.map_err(|_| Status::Unauthorized)?;
+	code_hosts: &CodeHosts,
+	user: &IdentityUser,
+) -> Result<(), ParticipateError> {
+	let cid = user.cid.clone();
+	let now = timestamp::now();
+	// Registration is checked before asking the code host about the repo, and
+	// again once the year is locked.
+	let year = data.year;
+	conn.run(move |c| check_registration(c, &cid, year, now))
+		.await?;
+
+	data.repo = match data.repo.as_deref().map(str::trim) {
+		Some(repo) if !repo.is_empty() => Some(check_repo(repo, code_hosts).await?),
+		_ => None,
+	};
+
+	let cid = user.cid.clone();
 	conn.run(move |c| {
-		diesel::insert_into(participants::table)
-			.values(Participant {
-				cid: user.cid,
-				year: data.year,
-				github: data.github.clone(),
-			})
-			.on_conflict((participants::columns::cid, participants::columns::year))
-			.do_update()
-			.set(participants::columns::github.eq(data.github))
-			.execute(c)
+		c.transaction::<_, ParticipateError, _>(|| {
+			// The year stays locked until they have joined, so registration
+			// can't close in between.
+			check_registration(c, &cid, data.year, now)?;
+
+			// Changing the repo later doesn't change when they joined.
+			diesel::insert_into(participants::table)
+				.values(Participant {
+					cid,
+					year: data.year,
+					repo: data.repo.clone(),
+					joined_at: Some(now),
+				})
+				.on_conflict((participants::columns::cid, participants::columns::year))
+				.do_update()
+				.set(participants::columns::repo.eq(data.repo))
+				.execute(c)?;
+			Ok(())
+		})
 	})
 	.await
-	.map_err(|_| Status::InternalServerError)?;
+}
+
+fn check_registration(
+	c: &PgConnection,
+	cid: &str,
+	year: i32,
+	now: i64,
+) -> Result<(), ParticipateError> {
+	let year_db: Year = years::table
+		.find(year)
+		.for_update()
+		.first(c)
+		.optional()?
+		.ok_or(Status::NotFound)?;
+	// Those already taking part can still change their repo once registration
+	// closes.
+	let participating: i64 = participants::table
+		.filter(participants::columns::cid.eq(cid))
+		.filter(participants::columns::year.eq(year))
+		.count()
+		.get_result(c)?;
+	if participating == 0 && !year_db.registration_open(now) {
+		return Err(ParticipateError::Closed);
+	}
 	Ok(())
 }
 
+// Makes sure the languages leaderboard will be able to read the repo, and
+// gives it the way it's stored: "owner/repo" on GitHub, or else the URL of
+// the repo. Links to pages within the repo and clone URLs are accepted.
+async fn check_repo(repo: &str, code_hosts: &CodeHosts) -> Result<String, RepoError> {
+	let repo = repo.trim_end_matches('/');
+	let repo = repo.strip_suffix(".git").unwrap_or(repo);
+	let repo = match repo
+		.strip_prefix("git@")
+		.and_then(|repo| repo.split_once(':'))
+	{
+		Some((host, path)) => format!("https://{}/{}", host, path),
+		None => repo.to_owned(),
+	};
+	let is_name = |part: &str| {
+		!part.is_empty()
+			&& part
+				.chars()
+				.all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+	};
+	if !repo.contains("://") && !(repo.split('/').count() == 2 && repo.split('/').all(is_name)) {
+		return Err(RepoError::Invalid);
+	}
+
+	let (host, path) = code_hosts.find(&repo).ok_or(RepoError::UnknownHost)?;
+	if path.split('/').count() < 2 || !path.split('/').all(is_name) {
+		return Err(RepoError::Invalid);
+	}
+	let found = host
+		.get_repo(path)
+		.await
+		.map_err(|e| {
+			println!(
+				"Could not look up repo {:?} ({}:{})\n\t{:?}",
+				repo,
+				file!(),
+				line!(),
+				e
+			);
+			RepoError::Unavailable
+		})?
+		.ok_or(RepoError::NotFound)?;
+	if !found.public {
+		return Err(RepoError::Private);
+	}
+
+	if host.url() == code_hosts.github().url() {
+		Ok(found.path)
+	} else {
+		Ok(format!("{}/{}", host.url(), found.path))
+	}
+}
+
 pub async fn delete_participation(
 	data: ParticipateDeleteRequest,
 	conn: &DbConn,
-	cookies: &CookieJar<'_>,
-	gamma_client: &GammaClient,
+	user: &IdentityUser,
 ) -> Result<(), Status> {
-	let access_cookie = cookies
-		.get(GammaClient::cookie())
-		.ok_or(Status::Unauthorized)?;
-	let user = gamma_client
-		.get_me(access_cookie.value())
+	let cid = user.cid.clone();
+	let rows_deleted = conn
+		.run(move |c| {
+			diesel::delete(participants::table)
+				.filter(participants::columns::cid.eq(cid))
+				.filter(participants::columns::year.eq(data.year))
+				.execute(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	if rows_deleted == 1 {
+		Ok(())
+	} else {
+		Err(Status::NotFound)
+	}
+}
+
+pub async fn get_year_participants(
+	year: i32,
+	conn: &DbConn,
+	user: &Authorized,
+) -> Result<Vec<YearParticipantResponse>, Status> {
+	user.require(Role::Moderator, Some(year))?;
+
+	let mut participants_db: Vec<Participant> = conn
+		.run(move |c| {
+			participants::table
+				.filter(participants::columns::year.eq(year))
+				.order(participants::columns::cid)
+				.load(c)
+		})
 		.await
-		.map_err(|_| Status::Unauthorized)?;
+		.map_err(|_| Status::InternalServerError)?;
+	Ok(participants_db
+		.drain(..)
+		.map(|p| YearParticipantResponse {
+			cid: p.cid,
+			repo: p.repo,
+		})
+		.collect())
+}
+
+pub async fn remove_participant(
+	data: ParticipantRemoveRequest,
+	conn: &DbConn,
+	user: &Authorized,
+) -> Result<(), Status> {
+	user.require(Role::Moderator, Some(data.year))?;
+
 	let rows_deleted = conn
 		.run(move |c| {
 			diesel::delete(participants::table)
-				.filter(participants::columns::cid.eq(user.cid))
+				.filter(participants::columns::cid.eq(data.cid))
 				.filter(participants::columns::year.eq(data.year))
 				.execute(c)
 		})
@@ -105,8 +241,10 @@ pub async fn delete_participation(
 #[serde(rename_all = "camelCase")]
 pub struct ParticipateRequest {
 	pub year: i32,
-	#[serde(default)]
-	pub github: Option<String>,
+	// Repos used to be GitHub only, the API still calls them that but takes
+	// either name.
+	#[serde(default, rename = "github", alias = "repo")]
+	pub repo: Option<String>,
 }
 
 pub type ParticipateResponse = ParticipateRequest;
@@ -116,3 +254,75 @@ pub type ParticipateResponse = ParticipateRequest;
 pub struct ParticipateDeleteRequest {
 	pub year: i32,
 }
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YearParticipantResponse {
+	pub cid: String,
+	pub repo: Option<String>,
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct ParticipantRemoveRequest {
+	pub year: i32,
+	pub cid: String,
+}
+
+#[derive(Debug)]
+pub enum RepoError {
+	Invalid,
+	UnknownHost,
+	NotFound,
+	Private,
+	Unavailable,
+}
+
+impl RepoError {
+	pub fn message(&self) -> &'static str {
+		match self {
+			RepoError::Invalid => "Enter the URL of your repo, or username/repo on GitHub.",
+			RepoError::UnknownHost => "The repo isn't on GitHub or any other host we know.",
+			RepoError::NotFound => "Could not find the repo, check that the URL is right.",
+			RepoError::Private => "The repo is private, make it public to compete with it.",
+			RepoError::Unavailable => "Could not check the repo right now, try again later.",
+		}
+	}
+}
+
+// Problems with the repo are told to the user, other errors are only a status.
+#[derive(Debug)]
+pub enum ParticipateError {
+	Repo(RepoError),
+	Closed,
+	Status(Status),
+}
+
+impl ParticipateError {
+	pub fn status(&self) -> Status {
+		match self {
+			ParticipateError::Repo(RepoError::Unavailable) => Status::ServiceUnavailable,
+			ParticipateError::Repo(_) => Status::BadRequest,
+			ParticipateError::Closed => Status::Forbidden,
+			ParticipateError::Status(status) => *status,
+		}
+	}
+}
+
+impl From<RepoError> for ParticipateError {
+	fn from(err: RepoError) -> Self {
+		ParticipateError::Repo(err)
+	}
+}
+
+impl From<diesel::result::Error> for ParticipateError {
+	fn from(_: diesel::result::Error) -> Self {
+		ParticipateError::Status(Status::InternalServerError)
+	}
+}
+
+impl From<Status> for ParticipateError {
+	fn from(status: Status) -> Self {
+		ParticipateError::Status(status)
+	}
+}
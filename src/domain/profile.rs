@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use r2d2_redis::redis::{cmd, pipe, Commands};
+use rocket::{
+	http::Status,
+	serde::{json::serde_json, Deserialize, Serialize},
+};
+
+use crate::{
+	config::Settings,
+	identity::{IdentityError, IdentityProvider, IdentityUser},
+	redis::RedisConn,
+};
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+	pub nick: String,
+	pub avatar_url: String,
+	#[serde(skip)]
+	pub unavailable: bool,
+}
+
+fn profile_key(cid: &str) -> String {
+	format!("profile_{}", cid)
+}
+
+fn last_profile_key(cid: &str) -> String {
+	format!("profile_last_{}", cid)
+}
+
+async fn fetch_cached_profiles(
+	cids: &[String],
+	redis: &RedisConn,
+	key: fn(&str) -> String,
+) -> HashMap<String, Profile> {
+	if cids.is_empty() {
+		return HashMap::new();
+	}
+
+	let keys: Vec<_> = cids.iter().map(|cid| key(cid)).collect();
+	let cached = redis
+		.run(move |c| cmd("MGET").arg(keys).query::<Vec<Option<String>>>(&mut **c))
+		.await
+		.map_err(|e| {
+			println!("Redis error: {}", e);
+		})
+		.unwrap_or_default();
+
+	cids.iter()
+		.zip(cached)
+		.filter_map(|(cid, cached)| {
+			let profile = serde_json::from_str::<Profile>(&cached?)
+				.map_err(|e| {
+					println!("Malformatted redis value: {}", e);
+				})
+				.ok()?;
+			Some((cid.to_owned(), profile))
+		})
+		.collect()
+}
+
+async fn cache_profiles(redis: &RedisConn, settings: &Settings, profiles: &[(String, Profile)]) {
+	if profiles.is_empty() {
+		return;
+	}
+
+	let cache_time = settings.profile_cache_time();
+	let mut pipeline = pipe();
+	for (cid, profile) in profiles {
+		let cache = serde_json::to_string(profile).unwrap();
+		pipeline
+			.set_ex(profile_key(cid), &cache, cache_time)
+			.ignore()
+			.set(last_profile_key(cid), &cache)
+			.ignore();
+	}
+	let result = redis.run(move |c| pipeline.query::<()>(&mut **c)).await;
+
+	if let Err(err) = result {
+		println!("Could not cache profiles: {:?}", err);
+	}
+}
+
+// The last known profiles are kept, they are only used when the identity
+// provider can't be reached.
+pub async fn purge_profiles(redis: &RedisConn) -> Result<usize, Status> {
+	redis
+		.run(move |c| {
+			let keys: Vec<String> = c
+				.scan_match::<_, String>("profile_*")?
+				.filter(|key| !key.starts_with("profile_last_"))
+				.collect();
+			if keys.is_empty() {
+				return Ok(0);
+			}
+			c.del(keys)
+		})
+		.await
+		.map_err(|e| {
+			println!("Redis error: {}", e);
+			Status::InternalServerError
+		})
+}
+
+pub async fn remember_profile(user: &IdentityUser, redis: &RedisConn, settings: &Settings) {
+	cache_profiles(redis, settings, &[(user.cid.to_owned(), Profile {
+		nick: user.nick.to_owned(),
+		avatar_url: user.avatar_url.to_owned(),
+		unavailable: false,
+	})])
+	.await;
+}
+
+// For identity providers that can't look users up, the profile they had when
+// they last signed in. Groups aren't remembered.
+pub async fn remembered_user(cid: &str, redis: &RedisConn) -> IdentityUser {
+	let cids = [cid.to_owned()];
+	let profile = fetch_cached_profiles(&cids, redis, last_profile_key)
+		.await
+		.remove(cid);
+	IdentityUser {
+		cid: cid.to_owned(),
+		nick: profile
+			.as_ref()
+			.map_or_else(|| cid.to_owned(), |p| p.nick.to_owned()),
+		avatar_url: profile.map(|p| p.avatar_url).unwrap_or_default(),
+		groups: Vec::new(),
+	}
+}
+
+pub async fn get_profiles(
+	cids: Vec<String>,
+	redis: &RedisConn,
+	settings: &Settings,
+	identity_provider: &dyn IdentityProvider,
+) -> HashMap<String, Profile> {
+	let mut profiles = fetch_cached_profiles(&cids, redis, profile_key).await;
+
+	let mut fetched = Vec::new();
+	let mut failed = Vec::new();
+	for result in futures::future::join_all(
+		cids.into_iter()
+			.filter(|cid| !profiles.contains_key(cid))
+			.map(
+				async move |cid| match identity_provider.get_user(&cid).await {
+					Ok(user) => Ok((cid, Profile {
+						nick: user.nick,
+						avatar_url: user.avatar_url,
+						unavailable: false,
+					})),
+					// Providers that can't look users up only have the profiles
+					// remembered from signing in, which are no less available.
+					Err(IdentityError::Unsupported) => Err((cid, false)),
+					Err(e) => {
+						println!(
+							"Could not get user {} when loading profiles ({}:{})\n\t{:?}",
+							cid,
+							file!(),
+							line!(),
+							e
+						);
+						Err((cid, true))
+					}
+				},
+			),
+	)
+	.await
+	{
+		match result {
+			Ok(profile) => fetched.push(profile),
+			Err(cid) => failed.push(cid),
+		}
+	}
+
+	cache_profiles(redis, settings, &fetched).await;
+	profiles.extend(fetched);
+
+	let failed_cids: Vec<_> = failed.iter().map(|(cid, _)| cid.to_owned()).collect();
+	let mut last_known = fetch_cached_profiles(&failed_cids, redis, last_profile_key).await;
+	profiles.extend(failed.into_iter().map(|(cid, unavailable)| {
+		let mut profile = last_known.remove(&cid).unwrap_or_else(|| Profile {
+			nick: cid.to_owned(),
+			avatar_url: String::new(),
+			unavailable: false,
+		});
+		profile.unavailable = unavailable;
+		(cid, profile)
+	}));
+
+	profiles
+}
@@ -0,0 +1,213 @@
+use std::str::FromStr;
+
+use diesel::{
+	expression_methods::{BoolExpressionMethods, ExpressionMethods},
+	query_dsl::QueryDsl,
+	result::DatabaseErrorKind,
+	RunQueryDsl,
+};
+use rocket::{
+	form::{FromForm, FromFormField},
+	http::Status,
+	serde::{Deserialize, Serialize},
+};
+
+use crate::{
+	auth::Authorized,
+	config::Settings,
+	db::{roles, DbConn, NewRoleAssignment, RoleAssignment},
+	identity::IdentityUser,
+};
+
+#[derive(
+	Clone, Copy, Debug, Deserialize, Eq, FromFormField, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+	#[field(value = "moderator")]
+	Moderator,
+	#[field(value = "year-admin")]
+	YearAdmin,
+	#[field(value = "owner")]
+	Owner,
+}
+
+impl Role {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Role::Moderator => "moderator",
+			Role::YearAdmin => "year-admin",
+			Role::Owner => "owner",
+		}
+	}
+}
+
+impl FromStr for Role {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"moderator" => Ok(Role::Moderator),
+			"year-admin" => Ok(Role::YearAdmin),
+			"owner" => Ok(Role::Owner),
+			_ => Err(()),
+		}
+	}
+}
+
+#[derive(Clone, Copy)]
+pub struct RoleGrant {
+	pub role: Role,
+	pub year: Option<i32>,
+}
+
+impl RoleGrant {
+	pub fn allows(&self, role: Role, year: Option<i32>) -> bool {
+		self.role >= role && (self.role == Role::Owner || self.year.is_none() || self.year == year)
+	}
+}
+
+pub async fn get_role_grants(
+	user: &IdentityUser,
+	conn: &DbConn,
+	settings: &Settings,
+) -> Result<Vec<RoleGrant>, Status> {
+	let cid = user.cid.clone();
+	let groups = user.groups.clone();
+
+	let mut grants: Vec<_> = if groups.iter().any(|g| settings.is_owner_group(g)) {
+		vec![RoleGrant {
+			role: Role::Owner,
+			year: None,
+		}]
+	} else {
+		Vec::new()
+	};
+
+	let mut roles_db: Vec<RoleAssignment> = conn
+		.run(move |c| {
+			roles::table
+				.filter(
+					roles::columns::is_group
+						.eq(false)
+						.and(roles::columns::subject.eq(cid))
+						.or(roles::columns::is_group
+							.eq(true)
+							.and(roles::columns::subject.eq_any(groups))),
+				)
+				.load(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	grants.extend(roles_db.drain(..).filter_map(|r| {
+		Some(RoleGrant {
+			role: r.role.parse().ok()?,
+			year: r.year,
+		})
+	}));
+
+	Ok(grants)
+}
+
+pub async fn get_roles(conn: &DbConn, user: &Authorized) -> Result<Vec<RoleResponse>, Status> {
+	user.require(Role::Owner, None)?;
+
+	let mut roles_db: Vec<RoleAssignment> = conn
+		.run(move |c| roles::table.order(roles::columns::id).load(c))
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	Ok(roles_db
+		.drain(..)
+		.filter_map(|r| {
+			Some(RoleResponse {
+				id: r.id,
+				role: r.role.parse().ok()?,
+				subject: r.subject,
+				is_group: r.is_group,
+				year: r.year,
+			})
+		})
+		.collect())
+}
+
+pub async fn set_role(data: RoleRequest, conn: &DbConn, user: &Authorized) -> Result<(), Status> {
+	user.require(Role::Owner, None)?;
+
+	let subject = data.subject.trim().to_owned();
+	if subject.is_empty() || (data.role == Role::Owner && data.year.is_some()) {
+		return Err(Status::BadRequest);
+	}
+
+	conn.run(move |c| {
+		diesel::insert_into(roles::table)
+			.values(NewRoleAssignment {
+				subject,
+				is_group: data.is_group,
+				role: data.role.as_str().to_owned(),
+				year: data.year,
+			})
+			.execute(c)
+	})
+	.await
+	.map_err(|err| match err {
+		diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+			Status::Conflict
+		}
+		diesel::result::Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+			Status::NotFound
+		}
+		_ => Status::InternalServerError,
+	})?;
+	Ok(())
+}
+
+pub async fn delete_role(
+	data: RoleDeleteRequest,
+	conn: &DbConn,
+	user: &Authorized,
+) -> Result<(), Status> {
+	user.require(Role::Owner, None)?;
+
+	let rows_deleted = conn
+		.run(move |c| {
+			diesel::delete(roles::table)
+				.filter(roles::columns::id.eq(data.id))
+				.execute(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	if rows_deleted == 1 {
+		Ok(())
+	} else {
+		Err(Status::NotFound)
+	}
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleRequest {
+	pub subject: String,
+	#[field(name = "isGroup")]
+	#[serde(default)]
+	pub is_group: bool,
+	pub role: Role,
+	#[serde(default)]
+	pub year: Option<i32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleResponse {
+	pub id: i32,
+	pub subject: String,
+	pub is_group: bool,
+	pub role: Role,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub year: Option<i32>,
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleDeleteRequest {
+	pub id: i32,
+}
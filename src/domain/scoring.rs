@@ -0,0 +1,203 @@
+use std::{collections::HashMap, str::FromStr};
+
+use rocket::{
+	form::FromFormField,
+	serde::{Deserialize, Serialize},
+};
+
+use crate::{aoc::Leaderboard, db::Year};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, FromFormField, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Points {
+	// Like AoC, the first to get a star scores as many points as there are
+	// members, the second one point less and so on.
+	#[field(value = "local")]
+	#[default]
+	Local,
+	// Every star is worth one point, however late.
+	#[field(value = "stars")]
+	Stars,
+}
+
+impl Points {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Points::Local => "local",
+			Points::Stars => "stars",
+		}
+	}
+}
+
+impl FromStr for Points {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"local" => Ok(Points::Local),
+			"stars" => Ok(Points::Stars),
+			_ => Err(()),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, FromFormField, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TieBreaker {
+	#[field(value = "none")]
+	#[default]
+	None,
+	// Whoever got their last star first ranks higher.
+	#[field(value = "last-star")]
+	LastStar,
+}
+
+impl TieBreaker {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			TieBreaker::None => "none",
+			TieBreaker::LastStar => "last-star",
+		}
+	}
+}
+
+impl FromStr for TieBreaker {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"none" => Ok(TieBreaker::None),
+			"last-star" => Ok(TieBreaker::LastStar),
+			_ => Err(()),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, FromFormField, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SplitsTieBreaker {
+	#[field(value = "none")]
+	#[default]
+	None,
+	// Whoever took the least time between the stars of every day they count
+	// ranks higher.
+	#[field(value = "total-time")]
+	TotalTime,
+}
+
+impl SplitsTieBreaker {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SplitsTieBreaker::None => "none",
+			SplitsTieBreaker::TotalTime => "total-time",
+		}
+	}
+}
+
+impl FromStr for SplitsTieBreaker {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"none" => Ok(SplitsTieBreaker::None),
+			"total-time" => Ok(SplitsTieBreaker::TotalTime),
+			_ => Err(()),
+		}
+	}
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scoring {
+	pub participants_only: bool,
+	pub points: Points,
+	pub excluded_days: Vec<u8>,
+	pub tie_breaker: TieBreaker,
+	pub splits_tie_breaker: SplitsTieBreaker,
+	pub late_joiners: bool,
+}
+
+impl From<&Year> for Scoring {
+	fn from(year: &Year) -> Self {
+		Scoring {
+			participants_only: year.participants_only,
+			points: year.points.parse().unwrap_or_default(),
+			excluded_days: year.excluded_days.iter().map(|&d| d as u8).collect(),
+			tie_breaker: year.tie_breaker.parse().unwrap_or_default(),
+			splits_tie_breaker: year.splits_tie_breaker.parse().unwrap_or_default(),
+			late_joiners: year.late_joiners,
+		}
+	}
+}
+
+impl Scoring {
+	// Whether AoC's own local scores can be used as they are, as long as
+	// there is a single leaderboard. Tie-breakers only change the order.
+	pub fn is_default(&self) -> bool {
+		!self.participants_only
+			&& self.points == Points::Local
+			&& self.excluded_days.is_empty()
+			&& self.late_joiners
+	}
+
+	// Whether a star from `day`, gotten at `ts`, counts for someone who
+	// joined at `joined_at`.
+	pub fn counts(&self, day: u8, ts: u64, joined_at: Option<i64>) -> bool {
+		!self.excluded_days.contains(&day)
+			&& (self.late_joiners || joined_at.is_none_or(|joined_at| ts as i64 >= joined_at))
+	}
+
+	// Replaces the local scores of the members, `joined_at` holds when the
+	// participants among them joined by AoC id.
+	pub fn rescore(&self, leaderboard: &mut Leaderboard, joined_at: &HashMap<String, Option<i64>>) {
+		let total_members = leaderboard.members.len() as u16;
+		let mut scores: HashMap<String, u16> = HashMap::new();
+		let mut star_vec = Vec::new();
+		for day in 1..=25 {
+			let day_str = day.to_string();
+			for second in [false, true] {
+				star_vec.extend(leaderboard.members.iter().filter_map(|(id, m)| {
+					let d = m.completion_day_level.get(&day_str)?;
+					let ts = if second {
+						d.second_star_ts
+					} else {
+						d.first_star_ts
+					}?;
+					let joined_at = joined_at.get(id).copied().flatten();
+					self.counts(day, ts, joined_at).then_some((ts, id))
+				}));
+				star_vec.sort();
+
+				for (i, (_, id)) in star_vec.iter().enumerate() {
+					let points = match self.points {
+						Points::Local => total_members - i as u16,
+						Points::Stars => 1,
+					};
+					*scores.entry((*id).to_owned()).or_insert(0) += points;
+				}
+
+				star_vec.clear();
+			}
+		}
+
+		for (id, member) in leaderboard.members.iter_mut() {
+			member.local_score = scores.get(id).copied().unwrap_or(0);
+		}
+	}
+}
+
+// Competition ranking ("1, 2, 2, 4"), entries with the same key share the
+// place of the first of them. Expects the keys in order.
+pub fn ranks<K: PartialEq>(keys: impl Iterator<Item = K>) -> Vec<usize> {
+	let mut ranks = Vec::new();
+	let mut previous = None;
+	for (i, key) in keys.enumerate() {
+		let rank = match (&previous, ranks.last()) {
+			(Some(previous), Some(&rank)) if *previous == key => rank,
+			_ => i + 1,
+		};
+		ranks.push(rank);
+		previous = Some(key);
+	}
+	ranks
+}
@@ -0,0 +1,182 @@
+use diesel::{expression_methods::ExpressionMethods, RunQueryDsl};
+use rocket::{
+	form::FromForm,
+	http::Status,
+	serde::{Deserialize, Serialize},
+};
+
+use super::Role;
+use crate::{
+	aoc::{AocError, LeaderboardSource},
+	auth::Authorized,
+	code_host::CodeHosts,
+	config::{SettingKey, Settings},
+	db::{settings, DbConn, Setting},
+};
+
+// Secrets are held by the clients that use them, so they have to be handed
+// over whenever they change.
+fn apply_setting(
+	key: SettingKey,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	code_hosts: &CodeHosts,
+) {
+	match key {
+		SettingKey::AocSession => match leaderboard_source.set_session(settings.get(key)) {
+			Ok(()) | Err(AocError::Unsupported) => {}
+			Err(e) => println!(
+				"Could not set the AoC session ({}:{})\n\t{:?}",
+				file!(),
+				line!(),
+				e
+			),
+		},
+		SettingKey::GithubClientId | SettingKey::GithubClientSecret | SettingKey::GithubToken => {
+			code_hosts.github().set_credentials(
+				settings.get(SettingKey::GithubToken),
+				settings.get(SettingKey::GithubClientId),
+				settings.get(SettingKey::GithubClientSecret),
+			)
+		}
+		_ => {}
+	}
+}
+
+pub async fn load_settings(
+	conn: &DbConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	code_hosts: &CodeHosts,
+) -> Result<(), Status> {
+	let settings_db: Vec<Setting> = conn
+		.run(move |c| settings::table.load(c))
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	for setting in settings_db {
+		match setting.key.parse() {
+			Ok(key) => settings.set_override(key, Some(setting.value)),
+			Err(_) => println!(
+				"Ignoring unknown setting \"{}\" ({}:{})",
+				setting.key,
+				file!(),
+				line!()
+			),
+		}
+	}
+
+	for key in SettingKey::ALL {
+		apply_setting(key, settings, leaderboard_source, code_hosts);
+	}
+	Ok(())
+}
+
+pub fn get_settings(
+	settings: &Settings,
+	user: &Authorized,
+) -> Result<Vec<SettingResponse>, Status> {
+	user.require(Role::Owner, None)?;
+
+	Ok(SettingKey::ALL
+		.iter()
+		.map(|&key| {
+			let value = settings.get(key);
+			SettingResponse {
+				key,
+				is_secret: key.is_secret(),
+				is_set: value.is_some(),
+				is_overridden: settings.is_overridden(key),
+				value: value.filter(|_| !key.is_secret()),
+			}
+		})
+		.collect())
+}
+
+pub async fn set_setting(
+	data: SettingRequest,
+	conn: &DbConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	code_hosts: &CodeHosts,
+	user: &Authorized,
+) -> Result<(), Status> {
+	user.require(Role::Owner, None)?;
+
+	let key = data.key;
+	let value = key.normalize(&data.value).ok_or(Status::BadRequest)?;
+	if key == SettingKey::AocSession {
+		leaderboard_source
+			.set_session(Some(value.clone()))
+			.map_err(|e| match e {
+				AocError::Unsupported => Status::BadRequest,
+				_ => Status::InternalServerError,
+			})?;
+	}
+
+	let value_copy = value.clone();
+	conn.run(move |c| {
+		diesel::insert_into(settings::table)
+			.values(Setting {
+				key: key.as_str().to_owned(),
+				value: value_copy.clone(),
+			})
+			.on_conflict(settings::columns::key)
+			.do_update()
+			.set(settings::columns::value.eq(value_copy))
+			.execute(c)
+	})
+	.await
+	.map_err(|_| Status::InternalServerError)?;
+
+	settings.set_override(key, Some(value));
+	apply_setting(key, settings, leaderboard_source, code_hosts);
+	Ok(())
+}
+
+pub async fn delete_setting(
+	data: SettingDeleteRequest,
+	conn: &DbConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	code_hosts: &CodeHosts,
+	user: &Authorized,
+) -> Result<(), Status> {
+	user.require(Role::Owner, None)?;
+
+	let key = data.key;
+	conn.run(move |c| {
+		diesel::delete(settings::table)
+			.filter(settings::columns::key.eq(key.as_str()))
+			.execute(c)
+	})
+	.await
+	.map_err(|_| Status::InternalServerError)?;
+
+	settings.set_override(key, None);
+	apply_setting(key, settings, leaderboard_source, code_hosts);
+	Ok(())
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingRequest {
+	pub key: SettingKey,
+	pub value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingResponse {
+	pub key: SettingKey,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub value: Option<String>,
+	pub is_secret: bool,
+	pub is_set: bool,
+	pub is_overridden: bool,
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingDeleteRequest {
+	pub key: SettingKey,
+}
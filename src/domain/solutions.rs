@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use crate::{code_host::TreeEntry, config::Settings};
+
+// Languages by file extension, or by the whole file name for files like
+// Makefiles. Anything else, like READMEs and inputs, is no solution.
+const LANGUAGES: &[(&str, &str)] = &[
+	("adb", "Ada"),
+	("apl", "APL"),
+	("awk", "Awk"),
+	("bash", "Shell"),
+	("c", "C"),
+	("cabal", "Haskell"),
+	("cc", "C++"),
+	("clj", "Clojure"),
+	("cljs", "Clojure"),
+	("cob", "COBOL"),
+	("coffee", "CoffeeScript"),
+	("cpp", "C++"),
+	("cr", "Crystal"),
+	("cs", "C#"),
+	("css", "CSS"),
+	("cxx", "C++"),
+	("d", "D"),
+	("dart", "Dart"),
+	("dockerfile", "Dockerfile"),
+	("erl", "Erlang"),
+	("ex", "Elixir"),
+	("exs", "Elixir"),
+	("f90", "Fortran"),
+	("fs", "F#"),
+	("fsx", "F#"),
+	("go", "Go"),
+	("groovy", "Groovy"),
+	("h", "C"),
+	("hpp", "C++"),
+	("hs", "Haskell"),
+	("htm", "HTML"),
+	("html", "HTML"),
+	("idr", "Idris"),
+	("ipynb", "Jupyter Notebook"),
+	("java", "Java"),
+	("jl", "Julia"),
+	("js", "JavaScript"),
+	("kt", "Kotlin"),
+	("kts", "Kotlin"),
+	("lisp", "Common Lisp"),
+	("lua", "Lua"),
+	("m", "Objective-C"),
+	("makefile", "Makefile"),
+	("ml", "OCaml"),
+	("nim", "Nim"),
+	("php", "PHP"),
+	("pl", "Perl"),
+	("pro", "Prolog"),
+	("ps1", "PowerShell"),
+	("py", "Python"),
+	("r", "R"),
+	("rb", "Ruby"),
+	("rkt", "Racket"),
+	("rs", "Rust"),
+	("scala", "Scala"),
+	("scm", "Scheme"),
+	("sh", "Shell"),
+	("sql", "SQL"),
+	("swift", "Swift"),
+	("ts", "TypeScript"),
+	("v", "V"),
+	("vb", "Visual Basic"),
+	("zig", "Zig"),
+];
+
+// Finds the solution files of every day in a repo, by the size of each
+// language. Sizes are unknown if the host didn't tell the size of a file. Files
+// belong to the day of the outermost directory, or else the file name, that
+// matches one of `patterns`, where "{day}" stands for the number of the day.
+pub fn solution_languages(
+	tree: &[TreeEntry],
+	patterns: &[String],
+) -> HashMap<u8, HashMap<&'static str, Option<u64>>> {
+	let mut days: HashMap<u8, HashMap<&'static str, Option<u64>>> = HashMap::new();
+	for entry in tree {
+		if let Some((day, language, _)) = solution(entry, patterns) {
+			let bytes = days
+				.entry(day)
+				.or_default()
+				.entry(language)
+				.or_insert(Some(0));
+			*bytes = bytes.zip(entry.size).map(|(a, b)| a + b);
+		}
+	}
+	days
+}
+
+// The paths of the directories or files that hold the solutions of every day,
+// by the same rules as `solution_languages`.
+pub fn solution_paths<'t>(tree: &'t [TreeEntry], patterns: &[String]) -> HashMap<u8, Vec<&'t str>> {
+	let mut days: HashMap<u8, Vec<&str>> = HashMap::new();
+	for entry in tree {
+		if let Some((day, _, path)) = solution(entry, patterns) {
+			let paths = days.entry(day).or_default();
+			if !paths.contains(&path) {
+				paths.push(path);
+			}
+		}
+	}
+	days
+}
+
+// The day of a solution file, its language and the path that named the day.
+fn solution<'e>(entry: &'e TreeEntry, patterns: &[String]) -> Option<(u8, &'static str, &'e str)> {
+	if entry.kind != "blob" {
+		return None;
+	}
+	let (directories, file_name) = match entry.path.rsplit_once('/') {
+		Some((directories, file_name)) => (directories, file_name),
+		None => ("", entry.path.as_str()),
+	};
+	let language = language(file_name)?;
+	let mut end = 0;
+	for component in directories.split('/') {
+		end += component.len();
+		if let Some(day) = day(component, patterns) {
+			return Some((day, language, &entry.path[..end]));
+		}
+		end += 1;
+	}
+	let stem = file_name
+		.split_once('.')
+		.map_or(file_name, |(stem, _)| stem);
+	Some((day(stem, patterns)?, language, &entry.path))
+}
+
+fn language(file_name: &str) -> Option<&'static str> {
+	let file_name = file_name.to_lowercase();
+	let extension = file_name
+		.rsplit_once('.')
+		.map_or(file_name.as_str(), |(_, extension)| extension);
+	LANGUAGES
+		.iter()
+		.find(|(key, _)| *key == extension)
+		.map(|(_, language)| *language)
+}
+
+fn day(component: &str, patterns: &[String]) -> Option<u8> {
+	let component = component.to_lowercase();
+	patterns.iter().find_map(|pattern| {
+		let (prefix, suffix) = pattern.split_once("{day}")?;
+		let digits = component.strip_prefix(prefix)?.strip_suffix(suffix)?;
+		if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+			return None;
+		}
+		digits.parse().ok().filter(|day| (1..=25).contains(day))
+	})
+}
+
+// Which languages count towards the languages leaderboard. Names are
+// compared ignoring case, an empty allowlist allows every language.
+pub struct LanguageRules {
+	allowlist: Vec<String>,
+	denylist: Vec<String>,
+	min_bytes: u64,
+}
+
+impl From<&Settings> for LanguageRules {
+	fn from(settings: &Settings) -> Self {
+		let lowercase = |list: Vec<String>| list.iter().map(|l| l.to_lowercase()).collect();
+		LanguageRules {
+			allowlist: lowercase(settings.language_allowlist()),
+			denylist: lowercase(settings.language_denylist()),
+			min_bytes: settings.language_min_bytes(),
+		}
+	}
+}
+
+impl LanguageRules {
+	// Whether `bytes` of `language` in the solutions of a day count. The
+	// least number of bytes only applies when the size is known.
+	pub fn counts(&self, language: &str, bytes: Option<u64>) -> bool {
+		let language = language.to_lowercase();
+		bytes.is_none_or(|bytes| bytes >= self.min_bytes)
+			&& (self.allowlist.is_empty() || self.allowlist.contains(&language))
+			&& !self.denylist.contains(&language)
+	}
+}
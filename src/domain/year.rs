@@ -1,59 +1,73 @@
-use diesel::{expression_methods::ExpressionMethods, query_dsl::QueryDsl, RunQueryDsl};
+use diesel::{
+	expression_methods::ExpressionMethods,
+	query_dsl::QueryDsl,
+	BelongingToDsl,
+	Connection,
+	GroupedBy,
+	RunQueryDsl,
+};
 use rocket::{
 	form::FromForm,
-	http::{CookieJar, Status},
+	http::Status,
 	serde::{Deserialize, Serialize},
 };
 
+use super::{purge_leaderboards, Points, Role, Scoring, SplitsTieBreaker, TieBreaker};
 use crate::{
-	db::{years, DbConn, Year},
-	gamma::GammaClient,
+	auth::Authorized,
+	db::{leaderboards, years, DbConn, Year, YearLeaderboard},
+	redis::RedisConn,
+	timestamp,
 };
 
+// How long before a year starts it's offered to participants.
+const UPCOMING: i64 = 30 * 24 * 60 * 60;
+
 pub async fn get_years(conn: &DbConn) -> Result<Vec<YearResponse>, Status> {
-	let mut years_db: Vec<Year> = conn
-		.run(move |c| years::table.load(c))
+	let (years_db, leaderboards_db) = conn
+		.run(move |c| {
+			let years_db: Vec<Year> = years::table.load(c)?;
+			let leaderboards_db: Vec<YearLeaderboard> = YearLeaderboard::belonging_to(&years_db)
+				.order(leaderboards::columns::id)
+				.load(c)?;
+			Ok::<_, diesel::result::Error>((years_db, leaderboards_db))
+		})
 		.await
 		.map_err(|_| Status::InternalServerError)?;
+	let leaderboards_db = leaderboards_db.grouped_by(&years_db);
 	Ok(years_db
-		.drain(..)
-		.map(|y| YearResponse {
+		.into_iter()
+		.zip(leaderboards_db)
+		.map(|(y, l)| YearResponse {
 			year: y.year,
-			leaderboard: y.leaderboard_id().to_owned(),
+			leaderboards: l.into_iter().map(|l| l.id).collect(),
+			scoring: Scoring::from(&y),
+			dates: YearDates::from(&y),
+			registration_open: y.registration_open(timestamp::now()),
 		})
 		.collect())
 }
 
-pub async fn get_year(
-	year: i32,
-	conn: &DbConn,
-	cookies: &CookieJar<'_>,
-	gamma_client: &GammaClient,
-) -> Result<YearResponse, Status> {
-	let access_cookie = cookies
-		.get(GammaClient::cookie())
-		.ok_or(Status::Unauthorized)?;
-	let _user = gamma_client
-		.get_me(access_cookie.value())
-		.await
-		.map_err(|_| Status::Unauthorized)?;
-
-	let year: Year = conn
-		.run(move |c| years::table.filter(years::columns::year.eq(year)).first(c))
+// The year to show: the latest year that has started, or is about to start,
+// or else the first year to come.
+pub async fn get_current_year(conn: &DbConn) -> Result<Option<Year>, Status> {
+	let years_db: Vec<Year> = conn
+		.run(move |c| years::table.load(c))
 		.await
 		.map_err(|_| Status::InternalServerError)?;
-	Ok(YearResponse {
-		year: year.year,
-		leaderboard: year.leaderboard,
-	})
+	let now = timestamp::now();
+	let (started, upcoming): (Vec<_>, Vec<_>) = years_db
+		.into_iter()
+		.partition(|y| y.starts_at() - UPCOMING <= now);
+	Ok(started
+		.into_iter()
+		.max_by_key(Year::starts_at)
+		.or_else(|| upcoming.into_iter().min_by_key(Year::starts_at)))
 }
 
-pub async fn set_year(
-	data: YearRequest,
-	conn: &DbConn,
-	cookies: &CookieJar<'_>,
-	gamma_client: &GammaClient,
-) -> Result<(), Status> {
+// Adds a private leaderboard to the year, creating the year if it's new. A
+// leaderboard that the year already has gets the new code instead.
+pub async fn set_year(data: YearRequest, conn: &DbConn, user: &Authorized) -> Result<(), Status> {
 	let leaderboard_split: Vec<_> = data.leaderboard.split('-').collect();
 	if leaderboard_split.len() != 2
 		|| !leaderboard_split[0].chars().all(char::is_numeric)
@@ -62,31 +76,40 @@ pub async fn set_year(
 		return Err(Status::BadRequest);
 	}
 
-	let access_cookie = cookies
-		.get(GammaClient::cookie())
-		.ok_or(Status::Unauthorized)?;
-	let user = gamma_client
-		.get_me(access_cookie.value())
+	let year = data.year;
+	let year_exists: i64 = conn
+		.run(move |c| {
+			years::table
+				.filter(years::columns::year.eq(year))
+				.count()
+				.get_result(c)
+		})
 		.await
-		.map_err(|_| Status::Unauthorized)?;
-	if !user
-		.groups
-		.ok_or(Status::Forbidden)?
-		.iter()
-		.any(|g| GammaClient::is_owner(&g.super_group.name))
-	{
-		return Err(Status::Forbidden);
+		.map_err(|_| Status::InternalServerError)?;
+	if year_exists > 0 {
+		user.require(Role::YearAdmin, Some(year))?;
+	} else {
+		user.require(Role::Owner, None)?;
 	}
+
+	let leaderboard = YearLeaderboard {
+		year,
+		id: leaderboard_split[0].to_owned(),
+		secret: leaderboard_split[1].to_owned(),
+	};
 	conn.run(move |c| {
-		diesel::insert_into(years::table)
-			.values(Year {
-				year: data.year,
-				leaderboard: data.leaderboard.clone(),
-			})
-			.on_conflict(years::columns::year)
-			.do_update()
-			.set(years::columns::leaderboard.eq(data.leaderboard))
-			.execute(c)
+		c.transaction(|| {
+			diesel::insert_into(years::table)
+				.values(years::columns::year.eq(year))
+				.on_conflict_do_nothing()
+				.execute(c)?;
+			diesel::insert_into(leaderboards::table)
+				.values(&leaderboard)
+				.on_conflict((leaderboards::columns::year, leaderboards::columns::id))
+				.do_update()
+				.set(leaderboards::columns::secret.eq(&leaderboard.secret))
+				.execute(c)
+		})
 	})
 	.await
 	.map_err(|_| Status::InternalServerError)?;
@@ -96,24 +119,10 @@ pub async fn set_year(
 pub async fn delete_year(
 	data: YearDeleteRequest,
 	conn: &DbConn,
-	cookies: &CookieJar<'_>,
-	gamma_client: &GammaClient,
+	user: &Authorized,
 ) -> Result<(), Status> {
-	let access_cookie = cookies
-		.get(GammaClient::cookie())
-		.ok_or(Status::Unauthorized)?;
-	let user = gamma_client
-		.get_me(access_cookie.value())
-		.await
-		.map_err(|_| Status::Unauthorized)?;
-	if !user
-		.groups
-		.ok_or(Status::Forbidden)?
-		.iter()
-		.any(|g| GammaClient::is_owner(&g.super_group.name))
-	{
-		return Err(Status::Forbidden);
-	}
+	user.require(Role::Owner, None)?;
+
 	let rows_deleted = conn
 		.run(move |c| {
 			diesel::delete(years::table)
@@ -129,17 +138,199 @@ pub async fn delete_year(
 	}
 }
 
-#[derive(Deserialize, FromForm, Serialize)]
+pub async fn set_scoring(
+	data: ScoringRequest,
+	conn: &DbConn,
+	redis: &RedisConn,
+	user: &Authorized,
+) -> Result<(), Status> {
+	user.require(Role::YearAdmin, Some(data.year))?;
+	if data.excluded_days.iter().any(|day| !(1..=25).contains(day)) {
+		return Err(Status::BadRequest);
+	}
+
+	let year = data.year;
+	let rows_updated = conn
+		.run(move |c| {
+			diesel::update(years::table.filter(years::columns::year.eq(data.year)))
+				.set((
+					years::columns::participants_only.eq(data.participants_only),
+					years::columns::points.eq(data.points.as_str()),
+					years::columns::excluded_days.eq(data
+						.excluded_days
+						.iter()
+						.map(|&day| day as i32)
+						.collect::<Vec<_>>()),
+					years::columns::tie_breaker.eq(data.tie_breaker.as_str()),
+					years::columns::splits_tie_breaker.eq(data.splits_tie_breaker.as_str()),
+					years::columns::late_joiners.eq(data.late_joiners),
+				))
+				.execute(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	if rows_updated != 1 {
+		return Err(Status::NotFound);
+	}
+
+	// The cached leaderboards were scored the old way.
+	purge_leaderboards(redis, Some(year)).await?;
+	Ok(())
+}
+
+pub async fn set_year_dates(
+	data: YearDatesRequest,
+	conn: &DbConn,
+	user: &Authorized,
+) -> Result<(), Status> {
+	user.require(Role::YearAdmin, Some(data.year))?;
+	let date = |date: &Option<String>| match date.as_deref().map(str::trim) {
+		None | Some("") => Ok(None),
+		Some(date) => timestamp::parse(date).map(Some).ok_or(Status::BadRequest),
+	};
+	let starts_at = date(&data.starts_at)?;
+	let registration_opens = date(&data.registration_opens)?;
+	let registration_closes = date(&data.registration_closes)?;
+	if let (Some(opens), Some(closes)) = (registration_opens, registration_closes) {
+		if opens >= closes {
+			return Err(Status::BadRequest);
+		}
+	}
+
+	let rows_updated = conn
+		.run(move |c| {
+			diesel::update(years::table.filter(years::columns::year.eq(data.year)))
+				.set((
+					years::columns::starts_at.eq(starts_at),
+					years::columns::registration_opens.eq(registration_opens),
+					years::columns::registration_closes.eq(registration_closes),
+				))
+				.execute(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	if rows_updated == 1 {
+		Ok(())
+	} else {
+		Err(Status::NotFound)
+	}
+}
+
+pub async fn delete_leaderboard(
+	data: LeaderboardDeleteRequest,
+	conn: &DbConn,
+	user: &Authorized,
+) -> Result<(), Status> {
+	user.require(Role::YearAdmin, Some(data.year))?;
+
+	let rows_deleted = conn
+		.run(move |c| {
+			diesel::delete(leaderboards::table)
+				.filter(leaderboards::columns::year.eq(data.year))
+				.filter(leaderboards::columns::id.eq(data.leaderboard))
+				.execute(c)
+		})
+		.await
+		.map_err(|_| Status::InternalServerError)?;
+	if rows_deleted == 1 {
+		Ok(())
+	} else {
+		Err(Status::NotFound)
+	}
+}
+
+#[derive(Deserialize, FromForm)]
 #[serde(rename_all = "camelCase")]
 pub struct YearRequest {
 	pub year: i32,
 	pub leaderboard: String,
 }
 
-pub type YearResponse = YearRequest;
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YearResponse {
+	pub year: i32,
+	pub leaderboards: Vec<String>,
+	pub scoring: Scoring,
+	pub dates: YearDates,
+	pub registration_open: bool,
+}
+
+// Dates are told in RFC 3339, in UTC.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YearDates {
+	pub starts_at: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub registration_opens: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub registration_closes: Option<String>,
+}
+
+impl From<&Year> for YearDates {
+	fn from(year: &Year) -> Self {
+		YearDates {
+			starts_at: timestamp::format(year.starts_at()),
+			registration_opens: year.registration_opens.map(timestamp::format),
+			registration_closes: year.registration_closes.map(timestamp::format),
+		}
+	}
+}
 
 #[derive(Deserialize, FromForm)]
 #[serde(rename_all = "camelCase")]
 pub struct YearDeleteRequest {
 	pub year: i32,
 }
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardDeleteRequest {
+	pub year: i32,
+	pub leaderboard: String,
+}
+
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoringRequest {
+	pub year: i32,
+	#[field(name = "participantsOnly")]
+	#[serde(default)]
+	pub participants_only: bool,
+	#[serde(default)]
+	pub points: Points,
+	#[field(name = "excludedDays")]
+	#[serde(default)]
+	pub excluded_days: Vec<u8>,
+	#[field(name = "tieBreaker")]
+	#[serde(default)]
+	pub tie_breaker: TieBreaker,
+	#[field(name = "splitsTieBreaker")]
+	#[serde(default)]
+	pub splits_tie_breaker: SplitsTieBreaker,
+	// Left out of JSON it defaults to true, an unticked checkbox is false.
+	#[field(name = "lateJoiners")]
+	#[serde(default = "default_late_joiners")]
+	pub late_joiners: bool,
+}
+
+fn default_late_joiners() -> bool {
+	true
+}
+
+// Dates left out or empty are unset, a year without a start date starts when
+// its first puzzle unlocks.
+#[derive(Deserialize, FromForm)]
+#[serde(rename_all = "camelCase")]
+pub struct YearDatesRequest {
+	pub year: i32,
+	#[field(name = "startsAt")]
+	#[serde(default)]
+	pub starts_at: Option<String>,
+	#[field(name = "registrationOpens")]
+	#[serde(default)]
+	pub registration_opens: Option<String>,
+	#[field(name = "registrationCloses")]
+	#[serde(default)]
+	pub registration_closes: Option<String>,
+}
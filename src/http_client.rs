@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use reqwest::{Client, Error, Proxy};
+use rocket::serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct HttpConfig {
+	#[serde(default = "default_timeout")]
+	pub timeout: u64,
+	#[serde(default = "default_user_agent")]
+	pub user_agent: String,
+	pub contact: Option<String>,
+	pub proxy: Option<String>,
+}
+
+fn default_timeout() -> u64 {
+	10
+}
+
+fn default_user_agent() -> String {
+	"digIT-AoC-Server".to_owned()
+}
+
+impl HttpConfig {
+	fn user_agent(&self) -> String {
+		match &self.contact {
+			Some(contact) => format!("{} ({})", self.user_agent, contact),
+			None => self.user_agent.to_owned(),
+		}
+	}
+
+	pub fn client(&self) -> Result<Client, Error> {
+		let mut builder = Client::builder()
+			.timeout(Duration::from_secs(self.timeout))
+			.user_agent(self.user_agent());
+		if let Some(proxy) = &self.proxy {
+			builder = builder.proxy(Proxy::all(proxy)?);
+		}
+		builder.build()
+	}
+}
@@ -0,0 +1,159 @@
+use std::env;
+
+use oauth2::{
+	basic::BasicClient,
+	reqwest::async_http_client,
+	AuthUrl,
+	AuthorizationCode,
+	ClientId,
+	ClientSecret,
+	CsrfToken,
+	RedirectUrl,
+	TokenResponse,
+	TokenUrl,
+};
+use rocket::serde::Deserialize;
+
+use super::{IdentityError, IdentityProvider, IdentityUser};
+
+pub struct GammaProvider {
+	oauth_client: BasicClient,
+	reqwest_client: reqwest::Client,
+	api_base: String,
+	api_key: String,
+}
+
+impl GammaProvider {
+	pub fn new(
+		client_id: String,
+		client_secret: String,
+		redirect_url: String,
+		callback_url: String,
+		api_base: String,
+		api_key: String,
+	) -> Result<Self, String> {
+		let auth_url = format!("{}/api/oauth/authorize", redirect_url);
+		let token_url = format!("{}/oauth/token", api_base);
+		Ok(Self {
+			oauth_client: BasicClient::new(
+				ClientId::new(client_id),
+				Some(ClientSecret::new(client_secret)),
+				AuthUrl::new(auth_url.clone()).map_err(|_| {
+					format!(
+						"Invalid authorization endpoint URL. (Attempt: \"{}\")",
+						auth_url
+					)
+				})?,
+				Some(TokenUrl::new(token_url.clone()).map_err(|_| {
+					format!("Invalid token endpoint URL. (Attempt: \"{}\")", token_url)
+				})?),
+			)
+			.set_redirect_uri(
+				RedirectUrl::new(callback_url.clone()).map_err(|_| {
+					format!("Invalid redirect URL. (Attempt: \"{}\")", callback_url)
+				})?,
+			),
+			reqwest_client: reqwest::Client::new(),
+			api_base,
+			api_key,
+		})
+	}
+
+	pub fn from_env() -> Self {
+		GammaProvider::new(
+			env::var("GAMMA_CLIENT_ID").expect("Missing the GAMMA_CLIENT_ID environment variable."),
+			env::var("GAMMA_CLIENT_SECRET")
+				.expect("Missing the GAMMA_CLIENT_SECRET environment variable."),
+			env::var("GAMMA_REDIRECT_URL")
+				.expect("Missing the GAMMA_REDIRECT_URL environment variable."),
+			env::var("CALLBACK_URL").expect("Missing the CALLBACK_URL environment variable."),
+			format!(
+				"{}/api",
+				env::var("GAMMA_URL").expect("Missing the GAMMA_URL environment variable.")
+			),
+			env::var("GAMMA_API_KEY").expect("Missing the GAMMA_API_KEY environment variable."),
+		)
+		.unwrap_or_else(|e| panic!("Failed to create the OAuth client. {}", e))
+	}
+}
+
+#[rocket::async_trait]
+impl IdentityProvider for GammaProvider {
+	fn authorize_url(&self, back: String) -> String {
+		let auth_url = self.oauth_client.authorize_url(|| CsrfToken::new(back));
+		let (url, _) = auth_url.url();
+		url.as_str().to_string()
+	}
+
+	async fn get_token(&self, code: String) -> Result<String, IdentityError> {
+		let token_response = self
+			.oauth_client
+			.exchange_code(AuthorizationCode::new(code))
+			.request_async(async_http_client)
+			.await?;
+		Ok(token_response.access_token().secret().clone())
+	}
+
+	async fn get_me(&self, token: &str) -> Result<IdentityUser, IdentityError> {
+		Ok(self
+			.reqwest_client
+			.get(format!("{}/users/me", self.api_base))
+			.header("Authorization", format!("Bearer {}", token))
+			.send()
+			.await?
+			.error_for_status()?
+			.json::<ITUser>()
+			.await?
+			.into())
+	}
+
+	async fn get_user(&self, cid: &str) -> Result<IdentityUser, IdentityError> {
+		Ok(self
+			.reqwest_client
+			.get(format!("{}/users/{}", self.api_base, cid))
+			.header("Authorization", format!("pre-shared {}", self.api_key))
+			.send()
+			.await?
+			.error_for_status()?
+			.json::<ITUser>()
+			.await?
+			.into())
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ITUser {
+	cid: String,
+	nick: String,
+	avatar_url: String,
+	groups: Option<Vec<FKITGroup>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FKITGroup {
+	super_group: FKITSuperGroup,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FKITSuperGroup {
+	name: String,
+}
+
+impl From<ITUser> for IdentityUser {
+	fn from(user: ITUser) -> Self {
+		IdentityUser {
+			cid: user.cid,
+			nick: user.nick,
+			avatar_url: user.avatar_url,
+			groups: user
+				.groups
+				.unwrap_or_default()
+				.into_iter()
+				.map(|g| g.super_group.name)
+				.collect(),
+		}
+	}
+}
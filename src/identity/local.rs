@@ -0,0 +1,64 @@
+use std::{env, fs};
+
+use rocket::{http::RawStr, serde::json::serde_json};
+
+use super::{IdentityError, IdentityProvider, IdentityUser};
+
+pub struct LocalProvider {
+	users: Vec<IdentityUser>,
+}
+
+impl LocalProvider {
+	pub fn new(path: &str) -> Result<Self, String> {
+		let users = fs::read_to_string(path)
+			.map_err(|e| format!("Could not read \"{}\". ({})", path, e))?;
+		let users = serde_json::from_str(&users)
+			.map_err(|e| format!("Could not parse \"{}\". ({})", path, e))?;
+		Ok(Self {
+			users,
+		})
+	}
+
+	pub fn from_env() -> Self {
+		LocalProvider::new(
+			&env::var("LOCAL_USERS_FILE")
+				.expect("Missing the LOCAL_USERS_FILE environment variable."),
+		)
+		.unwrap_or_else(|e| panic!("Failed to load the local users. {}", e))
+	}
+
+	fn find(&self, cid: &str) -> Result<IdentityUser, IdentityError> {
+		self.users
+			.iter()
+			.find(|u| u.cid == cid)
+			.cloned()
+			.ok_or(IdentityError::NotFound)
+	}
+}
+
+// The local provider is meant for development only. The "token" handed out is
+// simply the cid of the chosen user, so anyone may sign in as anyone.
+#[rocket::async_trait]
+impl IdentityProvider for LocalProvider {
+	fn authorize_url(&self, back: String) -> String {
+		format!("/login/local?state={}", RawStr::new(&back).percent_encode())
+	}
+
+	async fn get_token(&self, code: String) -> Result<String, IdentityError> {
+		self.find(&code)
+			.map(|u| u.cid)
+			.map_err(|_| IdentityError::Unauthorized)
+	}
+
+	async fn get_me(&self, token: &str) -> Result<IdentityUser, IdentityError> {
+		self.find(token).map_err(|_| IdentityError::Unauthorized)
+	}
+
+	async fn get_user(&self, cid: &str) -> Result<IdentityUser, IdentityError> {
+		self.find(cid)
+	}
+
+	fn local_users(&self) -> Option<&[IdentityUser]> {
+		Some(&self.users)
+	}
+}
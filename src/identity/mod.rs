@@ -0,0 +1,143 @@
+mod gamma;
+mod local;
+mod oidc;
+
+use std::{
+	env,
+	error::Error,
+	fmt::{self, Display, Formatter},
+};
+
+pub use gamma::GammaProvider;
+use lazy_static::lazy_static;
+pub use local::LocalProvider;
+use oauth2::{ErrorResponse, RequestTokenError};
+pub use oidc::OidcProvider;
+use reqwest::StatusCode;
+use rocket::{
+	request::{FromRequest, Outcome},
+	serde::{Deserialize, Serialize},
+	Request,
+};
+
+lazy_static! {
+	static ref IDENTITY_PROVIDER: Box<dyn IdentityProvider> = {
+		let provider = env::var("IDENTITY_PROVIDER").unwrap_or_else(|_| "gamma".to_owned());
+		match provider.as_str() {
+			"gamma" => Box::new(GammaProvider::from_env()),
+			"oidc" => Box::new(OidcProvider::from_env()),
+			"local" => Box::new(LocalProvider::from_env()),
+			_ => panic!(
+				"Unknown IDENTITY_PROVIDER \"{}\", expected one of gamma, oidc or local.",
+				provider
+			),
+		}
+	};
+	static ref SESSION_COOKIE: String = env::var("SESSION_COOKIE")
+		.or_else(|_| env::var("GAMMA_COOKIE"))
+		.expect("Missing the SESSION_COOKIE environment variable.");
+}
+
+#[rocket::async_trait]
+pub trait IdentityProvider: Send + Sync {
+	fn authorize_url(&self, back: String) -> String;
+
+	async fn get_token(&self, code: String) -> Result<String, IdentityError>;
+
+	async fn get_me(&self, token: &str) -> Result<IdentityUser, IdentityError>;
+
+	async fn get_user(&self, cid: &str) -> Result<IdentityUser, IdentityError>;
+
+	fn local_users(&self) -> Option<&[IdentityUser]> {
+		None
+	}
+}
+
+pub fn provider() -> &'static dyn IdentityProvider {
+	IDENTITY_PROVIDER.as_ref()
+}
+
+pub fn cookie() -> &'static str {
+	&SESSION_COOKIE
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'static dyn IdentityProvider {
+	type Error = ();
+
+	async fn from_request(_request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		Outcome::Success(provider())
+	}
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityUser {
+	pub cid: String,
+	pub nick: String,
+	#[serde(default)]
+	pub avatar_url: String,
+	#[serde(default)]
+	pub groups: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum IdentityError {
+	Unauthorized,
+	NotFound,
+	Unsupported,
+	RequestError(Box<dyn Error + Send + Sync>),
+	ClientError(Box<dyn Error + Send + Sync>),
+	UnknownError(Box<dyn Error + Send + Sync>),
+}
+
+impl From<reqwest::Error> for IdentityError {
+	fn from(err: reqwest::Error) -> Self {
+		match err.status() {
+			Some(StatusCode::UNAUTHORIZED) => IdentityError::Unauthorized,
+			Some(StatusCode::NOT_FOUND) => IdentityError::NotFound,
+			Some(_) => IdentityError::RequestError(Box::new(err)),
+			None if err.is_decode() => IdentityError::ClientError(Box::new(err)),
+			None => IdentityError::UnknownError(Box::new(err)),
+		}
+	}
+}
+
+impl<TE, TR> From<RequestTokenError<TE, TR>> for IdentityError
+where
+	TE: Error + Send + Sync + 'static,
+	TR: ErrorResponse + Send + Sync + 'static,
+{
+	fn from(err: RequestTokenError<TE, TR>) -> Self {
+		match err {
+			RequestTokenError::ServerResponse(_) => IdentityError::UnknownError(Box::new(err)),
+			RequestTokenError::Request(err) => IdentityError::RequestError(Box::new(err)),
+			RequestTokenError::Parse(_, _) => IdentityError::ClientError(Box::new(err)),
+			RequestTokenError::Other(_) => IdentityError::UnknownError(Box::new(err)),
+		}
+	}
+}
+
+impl Error for IdentityError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			IdentityError::RequestError(err) => Some(err.as_ref()),
+			IdentityError::ClientError(err) => Some(err.as_ref()),
+			IdentityError::UnknownError(err) => Some(err.as_ref()),
+			_ => None,
+		}
+	}
+}
+
+impl Display for IdentityError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			IdentityError::Unauthorized => write!(f, "Unauthorized"),
+			IdentityError::NotFound => write!(f, "Not found"),
+			IdentityError::Unsupported => write!(f, "Unsupported by the identity provider"),
+			IdentityError::RequestError(_) => write!(f, "Request error"),
+			IdentityError::ClientError(_) => write!(f, "Client error"),
+			IdentityError::UnknownError(err) => write!(f, "Unknown error ({})", err),
+		}
+	}
+}
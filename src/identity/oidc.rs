@@ -0,0 +1,159 @@
+use std::env;
+
+use oauth2::{
+	basic::BasicClient,
+	reqwest::async_http_client,
+	AuthUrl,
+	AuthorizationCode,
+	ClientId,
+	ClientSecret,
+	CsrfToken,
+	RedirectUrl,
+	Scope,
+	TokenResponse,
+	TokenUrl,
+};
+use rocket::serde::json::Value;
+
+use super::{IdentityError, IdentityProvider, IdentityUser};
+
+pub struct OidcProvider {
+	oauth_client: BasicClient,
+	reqwest_client: reqwest::Client,
+	userinfo_url: String,
+	scopes: Vec<String>,
+	cid_claim: String,
+	nick_claim: String,
+	groups_claim: String,
+}
+
+impl OidcProvider {
+	pub fn new(
+		client_id: String,
+		client_secret: String,
+		auth_url: String,
+		token_url: String,
+		userinfo_url: String,
+		callback_url: String,
+	) -> Result<Self, String> {
+		Ok(Self {
+			oauth_client: BasicClient::new(
+				ClientId::new(client_id),
+				Some(ClientSecret::new(client_secret)),
+				AuthUrl::new(auth_url.clone()).map_err(|_| {
+					format!(
+						"Invalid authorization endpoint URL. (Attempt: \"{}\")",
+						auth_url
+					)
+				})?,
+				Some(TokenUrl::new(token_url.clone()).map_err(|_| {
+					format!("Invalid token endpoint URL. (Attempt: \"{}\")", token_url)
+				})?),
+			)
+			.set_redirect_uri(
+				RedirectUrl::new(callback_url.clone()).map_err(|_| {
+					format!("Invalid redirect URL. (Attempt: \"{}\")", callback_url)
+				})?,
+			),
+			reqwest_client: reqwest::Client::new(),
+			userinfo_url,
+			scopes: vec!["openid".to_owned(), "profile".to_owned()],
+			cid_claim: "sub".to_owned(),
+			nick_claim: "nickname".to_owned(),
+			groups_claim: "groups".to_owned(),
+		})
+	}
+
+	pub fn from_env() -> Self {
+		let mut provider = OidcProvider::new(
+			env::var("OIDC_CLIENT_ID").expect("Missing the OIDC_CLIENT_ID environment variable."),
+			env::var("OIDC_CLIENT_SECRET")
+				.expect("Missing the OIDC_CLIENT_SECRET environment variable."),
+			env::var("OIDC_AUTH_URL").expect("Missing the OIDC_AUTH_URL environment variable."),
+			env::var("OIDC_TOKEN_URL").expect("Missing the OIDC_TOKEN_URL environment variable."),
+			env::var("OIDC_USERINFO_URL")
+				.expect("Missing the OIDC_USERINFO_URL environment variable."),
+			env::var("CALLBACK_URL").expect("Missing the CALLBACK_URL environment variable."),
+		)
+		.unwrap_or_else(|e| panic!("Failed to create the OAuth client. {}", e));
+
+		if let Ok(scopes) = env::var("OIDC_SCOPES") {
+			provider.scopes = scopes.split_whitespace().map(str::to_owned).collect();
+		}
+		if let Ok(claim) = env::var("OIDC_CID_CLAIM") {
+			provider.cid_claim = claim;
+		}
+		if let Ok(claim) = env::var("OIDC_NICK_CLAIM") {
+			provider.nick_claim = claim;
+		}
+		if let Ok(claim) = env::var("OIDC_GROUPS_CLAIM") {
+			provider.groups_claim = claim;
+		}
+		provider
+	}
+
+	fn user_from_claims(&self, claims: &Value) -> Result<IdentityUser, IdentityError> {
+		let claim = |name: &str| claims.get(name).and_then(Value::as_str).map(str::to_owned);
+		// Users can often change claims like their username, which would let
+		// them take over someone else's cid. Only the configured claim is used.
+		let cid = claim(&self.cid_claim).ok_or(IdentityError::Unauthorized)?;
+		Ok(IdentityUser {
+			nick: claim(&self.nick_claim)
+				.or_else(|| claim("name"))
+				.unwrap_or_else(|| cid.clone()),
+			avatar_url: claim("picture").unwrap_or_default(),
+			groups: claims
+				.get(&self.groups_claim)
+				.and_then(Value::as_array)
+				.map(|groups| {
+					groups
+						.iter()
+						.filter_map(Value::as_str)
+						.map(str::to_owned)
+						.collect()
+				})
+				.unwrap_or_default(),
+			cid,
+		})
+	}
+}
+
+#[rocket::async_trait]
+impl IdentityProvider for OidcProvider {
+	fn authorize_url(&self, back: String) -> String {
+		let auth_url = self
+			.oauth_client
+			.authorize_url(|| CsrfToken::new(back))
+			.add_scopes(self.scopes.iter().cloned().map(Scope::new));
+		let (url, _) = auth_url.url();
+		url.as_str().to_string()
+	}
+
+	async fn get_token(&self, code: String) -> Result<String, IdentityError> {
+		let token_response = self
+			.oauth_client
+			.exchange_code(AuthorizationCode::new(code))
+			.request_async(async_http_client)
+			.await?;
+		Ok(token_response.access_token().secret().clone())
+	}
+
+	async fn get_me(&self, token: &str) -> Result<IdentityUser, IdentityError> {
+		let claims = self
+			.reqwest_client
+			.get(&self.userinfo_url)
+			.header("Authorization", format!("Bearer {}", token))
+			.send()
+			.await?
+			.error_for_status()?
+			.json::<Value>()
+			.await?;
+		self.user_from_claims(&claims)
+	}
+
+	async fn get_user(&self, _cid: &str) -> Result<IdentityUser, IdentityError> {
+		// OpenID Connect has no standard way of looking up other users, their
+		// profiles are remembered when they sign in instead.
+		Err(IdentityError::Unsupported)
+	}
+}
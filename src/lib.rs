@@ -0,0 +1,1183 @@
+#![feature(proc_macro_hygiene)]
+
+pub mod admin;
+mod aoc;
+mod auth;
+mod code_host;
+mod config;
+mod db;
+mod domain;
+mod http_client;
+mod identity;
+mod redis;
+mod timestamp;
+
+#[macro_use]
+extern crate diesel;
+#[macro_use]
+extern crate rocket;
+
+use std::cmp::Reverse;
+
+use aoc::LeaderboardSource;
+use auth::{AuthUser, Authorized};
+use code_host::CodeHosts;
+use config::Settings;
+use db::{migrations, DbConn};
+use diesel::{Connection, PgConnection};
+use domain::{
+	create_api_token,
+	delete_api_token,
+	delete_leaderboard,
+	delete_participation,
+	delete_role,
+	delete_setting,
+	delete_year,
+	get_aoc_id,
+	get_api_tokens,
+	get_current_year,
+	get_join_code,
+	get_leaderboard,
+	get_leaderboard_commits,
+	get_leaderboard_languages,
+	get_leaderboard_splits,
+	get_participations,
+	get_roles,
+	get_settings,
+	get_year_participants,
+	get_years,
+	load_settings,
+	remember_profile,
+	remove_participant,
+	set_aoc_id,
+	set_participation,
+	set_role,
+	set_scoring,
+	set_setting,
+	set_year,
+	set_year_dates,
+	AdminContext,
+	AdminYearContext,
+	AocIdRequest,
+	AocIdResponse,
+	ApiTokenCreatedResponse,
+	ApiTokenDeleteRequest,
+	ApiTokenRequest,
+	ApiTokenResponse,
+	ConfigContext,
+	Context,
+	JsonOrTemplateLeaderboard,
+	LeaderboardCommitsResponse,
+	LeaderboardContext,
+	LeaderboardDeleteRequest,
+	LeaderboardLanguagesResponse,
+	LeaderboardSplitsResponse,
+	LocalLoginContext,
+	ParticipantRemoveRequest,
+	ParticipateDeleteRequest,
+	ParticipateError,
+	ParticipateRequest,
+	ParticipateResponse,
+	ParticipationContext,
+	Role,
+	RoleDeleteRequest,
+	RoleRequest,
+	RoleResponse,
+	ScoringRequest,
+	SettingDeleteRequest,
+	SettingRequest,
+	SettingResponse,
+	SettingsContext,
+	YearDatesRequest,
+	YearDeleteRequest,
+	YearParticipantResponse,
+	YearRequest,
+	YearResponse,
+};
+use identity::IdentityProvider;
+use redis::RedisConn;
+use rocket::{
+	fairing::AdHoc,
+	figment::Provider,
+	form::Form,
+	fs::FileServer,
+	http::{uri::Origin, Cookie, CookieJar, Status},
+	request::FlashMessage,
+	response::{Flash, Redirect},
+	serde::{json::Json, Serialize},
+	Build,
+	Config,
+	Rocket,
+};
+use rocket_dyn_templates::Template;
+
+async fn create_base_context<T: Serialize>(
+	data: T,
+	cookies: &CookieJar<'_>,
+	identity_provider: &dyn IdentityProvider,
+) -> Context<T> {
+	if let Some(access_cookie) = cookies.get(identity::cookie()) {
+		if let Ok(user) = identity_provider.get_me(access_cookie.value()).await {
+			return Context {
+				current_nick: Some(user.nick),
+				data,
+			};
+		}
+	}
+
+	Context {
+		current_nick: None,
+		data,
+	}
+}
+
+#[get("/login?<back>")]
+async fn login(
+	back: Option<String>,
+	cookies: &CookieJar<'_>,
+	identity_provider: &dyn IdentityProvider,
+) -> Redirect {
+	if let Some(access_cookie) = cookies.get(identity::cookie()) {
+		if identity_provider
+			.get_me(access_cookie.value())
+			.await
+			.is_ok()
+		{
+			return Redirect::to(back.unwrap_or_else(|| "/".to_string()));
+		}
+	}
+	Redirect::to(identity_provider.authorize_url(back.unwrap_or_else(|| "/".to_string())))
+}
+
+#[get("/login/local?<state>")]
+async fn login_local(
+	state: Option<String>,
+	cookies: &CookieJar<'_>,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<Template, Status> {
+	let users = identity_provider
+		.local_users()
+		.ok_or(Status::NotFound)?
+		.to_vec();
+	let context = create_base_context(
+		LocalLoginContext {
+			state: state.unwrap_or_else(|| "/".to_string()),
+			users,
+		},
+		cookies,
+		identity_provider,
+	)
+	.await;
+	Ok(Template::render("login-local", context))
+}
+
+#[get("/aoc-id.json")]
+async fn get_aoc_id_json(conn: DbConn, user: AuthUser) -> Result<Json<AocIdResponse>, Status> {
+	get_aoc_id(&conn, &user).await.map(Json)
+}
+
+#[post("/aoc-id.json", data = "<data>")]
+async fn post_aoc_id_json(
+	data: Json<AocIdRequest>,
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Status, Status> {
+	set_aoc_id(data.aoc_id.clone(), &conn, &user)
+		.await
+		.map(|_| Status::Ok)
+}
+
+#[post("/aoc-id", data = "<data>")]
+async fn post_aoc_id(
+	data: Form<AocIdRequest>,
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Redirect, Status> {
+	set_aoc_id(data.aoc_id.clone(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[get("/years.json")]
+async fn get_years_json(conn: DbConn) -> Result<Json<Vec<YearResponse>>, Status> {
+	get_years(&conn).await.map(Json)
+}
+
+#[post("/years.json", data = "<data>")]
+async fn post_years_json(
+	data: Json<YearRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Status, Status> {
+	set_year(data.0, &conn, &user).await.map(|_| Status::Ok)
+}
+
+#[post("/years", data = "<data>")]
+async fn post_years(
+	data: Form<YearRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	set_year(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[delete("/years.json", data = "<data>")]
+async fn delete_years_json(
+	data: Json<YearDeleteRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Status, Status> {
+	delete_year(data.0, &conn, &user).await.map(|_| Status::Ok)
+}
+
+#[post("/years-delete", data = "<data>")]
+async fn delete_years(
+	data: Form<YearDeleteRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	delete_year(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[post("/years/scoring.json", data = "<data>")]
+async fn post_years_scoring_json(
+	data: Json<ScoringRequest>,
+	conn: DbConn,
+	redis: RedisConn,
+	user: Authorized,
+) -> Result<Status, Status> {
+	set_scoring(data.0, &conn, &redis, &user)
+		.await
+		.map(|_| Status::Ok)
+}
+
+#[post("/years/scoring", data = "<data>")]
+async fn post_years_scoring(
+	data: Form<ScoringRequest>,
+	conn: DbConn,
+	redis: RedisConn,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	set_scoring(data.into_inner(), &conn, &redis, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[post("/years/dates.json", data = "<data>")]
+async fn post_years_dates_json(
+	data: Json<YearDatesRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Status, Status> {
+	set_year_dates(data.0, &conn, &user)
+		.await
+		.map(|_| Status::Ok)
+}
+
+#[post("/years/dates", data = "<data>")]
+async fn post_years_dates(
+	data: Form<YearDatesRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	set_year_dates(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[delete("/years/leaderboards.json", data = "<data>")]
+async fn delete_year_leaderboards_json(
+	data: Json<LeaderboardDeleteRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Status, Status> {
+	delete_leaderboard(data.0, &conn, &user)
+		.await
+		.map(|_| Status::Ok)
+}
+
+#[post("/years/leaderboards-delete", data = "<data>")]
+async fn delete_year_leaderboards(
+	data: Form<LeaderboardDeleteRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	delete_leaderboard(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[get("/years/<year>/participants.json")]
+async fn get_year_participants_json(
+	year: i32,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Json<Vec<YearParticipantResponse>>, Status> {
+	get_year_participants(year, &conn, &user).await.map(Json)
+}
+
+#[delete("/years/participants.json", data = "<data>")]
+async fn delete_year_participants_json(
+	data: Json<ParticipantRemoveRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Status, Status> {
+	remove_participant(data.0, &conn, &user)
+		.await
+		.map(|_| Status::Ok)
+}
+
+#[post("/years/participants-delete", data = "<data>")]
+async fn delete_year_participants(
+	data: Form<ParticipantRemoveRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	remove_participant(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[get("/roles.json")]
+async fn get_roles_json(conn: DbConn, user: Authorized) -> Result<Json<Vec<RoleResponse>>, Status> {
+	get_roles(&conn, &user).await.map(Json)
+}
+
+#[post("/roles.json", data = "<data>")]
+async fn post_roles_json(
+	data: Json<RoleRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Status, Status> {
+	set_role(data.0, &conn, &user).await.map(|_| Status::Ok)
+}
+
+#[post("/roles", data = "<data>")]
+async fn post_roles(
+	data: Form<RoleRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	set_role(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[delete("/roles.json", data = "<data>")]
+async fn delete_roles_json(
+	data: Json<RoleDeleteRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Status, Status> {
+	delete_role(data.0, &conn, &user).await.map(|_| Status::Ok)
+}
+
+#[post("/roles-delete", data = "<data>")]
+async fn delete_roles(
+	data: Form<RoleDeleteRequest>,
+	conn: DbConn,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	delete_role(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[get("/participate.json")]
+async fn get_participate_json(
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Json<Vec<ParticipateResponse>>, Status> {
+	get_participations(&conn, &user).await.map(Json)
+}
+
+#[post("/participate.json", data = "<data>")]
+async fn post_participate_json(
+	data: Json<ParticipateRequest>,
+	conn: DbConn,
+	code_hosts: &CodeHosts,
+	user: AuthUser,
+) -> Result<Status, Status> {
+	set_participation(data.0, &conn, code_hosts, &user)
+		.await
+		.map(|_| Status::Ok)
+		.map_err(|e| e.status())
+}
+
+// Problems with the repo are shown on the settings page, along with the year
+// they're about.
+#[post("/participate", data = "<data>")]
+async fn post_participate(
+	data: Form<ParticipateRequest>,
+	conn: DbConn,
+	code_hosts: &CodeHosts,
+	user: AuthUser,
+) -> Result<Result<Redirect, Flash<Redirect>>, Status> {
+	let year = data.year;
+	match set_participation(data.into_inner(), &conn, code_hosts, &user).await {
+		Ok(()) => Ok(Ok(Redirect::to(uri!(settings)))),
+		Err(ParticipateError::Repo(e)) => Ok(Err(Flash::error(
+			Redirect::to(uri!(settings)),
+			format!("{}: {}", year, e.message()),
+		))),
+		Err(ParticipateError::Closed) => Ok(Err(Flash::error(
+			Redirect::to(uri!(settings)),
+			format!("{}: Registration for this year is closed.", year),
+		))),
+		Err(ParticipateError::Status(status)) => Err(status),
+	}
+}
+
+#[delete("/participate.json", data = "<data>")]
+async fn delete_participate_json(
+	data: Json<ParticipateDeleteRequest>,
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Status, Status> {
+	delete_participation(data.0, &conn, &user)
+		.await
+		.map(|_| Status::Ok)
+}
+
+#[post("/participate-delete", data = "<data>")]
+async fn delete_participate(
+	data: Form<ParticipateDeleteRequest>,
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Redirect, Status> {
+	delete_participation(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[get("/api-tokens.json")]
+async fn get_api_tokens_json(
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Json<Vec<ApiTokenResponse>>, Status> {
+	get_api_tokens(&conn, &user).await.map(Json)
+}
+
+#[post("/api-tokens.json", data = "<data>")]
+async fn post_api_tokens_json(
+	data: Json<ApiTokenRequest>,
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Json<ApiTokenCreatedResponse>, Status> {
+	create_api_token(data.0, &conn, &user).await.map(Json)
+}
+
+#[post("/api-tokens", data = "<data>")]
+async fn post_api_tokens(
+	data: Form<ApiTokenRequest>,
+	conn: DbConn,
+	user: AuthUser,
+	cookies: &CookieJar<'_>,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<Template, Status> {
+	let token = create_api_token(data.into_inner(), &conn, &user).await?;
+	let context = create_base_context(token, cookies, identity_provider).await;
+	Ok(Template::render("api-token", context))
+}
+
+#[delete("/api-tokens.json", data = "<data>")]
+async fn delete_api_tokens_json(
+	data: Json<ApiTokenDeleteRequest>,
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Status, Status> {
+	delete_api_token(data.0, &conn, &user)
+		.await
+		.map(|_| Status::Ok)
+}
+
+#[post("/api-tokens-delete", data = "<data>")]
+async fn delete_api_tokens(
+	data: Form<ApiTokenDeleteRequest>,
+	conn: DbConn,
+	user: AuthUser,
+) -> Result<Redirect, Status> {
+	delete_api_token(data.into_inner(), &conn, &user).await?;
+	Ok(Redirect::to(uri!(settings)))
+}
+
+#[get("/config.json")]
+async fn get_config_json(
+	settings: &Settings,
+	user: Authorized,
+) -> Result<Json<Vec<SettingResponse>>, Status> {
+	get_settings(settings, &user).map(Json)
+}
+
+#[post("/config.json", data = "<data>")]
+async fn post_config_json(
+	data: Json<SettingRequest>,
+	conn: DbConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	code_hosts: &CodeHosts,
+	user: Authorized,
+) -> Result<Status, Status> {
+	set_setting(
+		data.0,
+		&conn,
+		settings,
+		leaderboard_source,
+		code_hosts,
+		&user,
+	)
+	.await
+	.map(|_| Status::Ok)
+}
+
+#[post("/config", data = "<data>")]
+async fn post_config(
+	data: Form<SettingRequest>,
+	conn: DbConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	code_hosts: &CodeHosts,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	set_setting(
+		data.into_inner(),
+		&conn,
+		settings,
+		leaderboard_source,
+		code_hosts,
+		&user,
+	)
+	.await?;
+	Ok(Redirect::to(uri!(get_config)))
+}
+
+#[delete("/config.json", data = "<data>")]
+async fn delete_config_json(
+	data: Json<SettingDeleteRequest>,
+	conn: DbConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	code_hosts: &CodeHosts,
+	user: Authorized,
+) -> Result<Status, Status> {
+	delete_setting(
+		data.0,
+		&conn,
+		settings,
+		leaderboard_source,
+		code_hosts,
+		&user,
+	)
+	.await
+	.map(|_| Status::Ok)
+}
+
+#[post("/config-delete", data = "<data>")]
+async fn delete_config(
+	data: Form<SettingDeleteRequest>,
+	conn: DbConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	code_hosts: &CodeHosts,
+	user: Authorized,
+) -> Result<Redirect, Status> {
+	delete_setting(
+		data.into_inner(),
+		&conn,
+		settings,
+		leaderboard_source,
+		code_hosts,
+		&user,
+	)
+	.await?;
+	Ok(Redirect::to(uri!(get_config)))
+}
+
+#[get("/leaderboard")]
+async fn redirect_leaderboard(conn: DbConn) -> Result<Redirect, Status> {
+	get_current_year(&conn)
+		.await?
+		.ok_or(Status::NotFound)
+		.map(|y| Redirect::found(uri!(get_leaderboard_year_json(y.year.to_string()))))
+}
+
+#[get("/leaderboard/<year>")]
+async fn get_leaderboard_year_json(
+	mut year: String,
+	conn: DbConn,
+	redis: RedisConn,
+	settings: &Settings,
+	cookies: &CookieJar<'_>,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<JsonOrTemplateLeaderboard, Status> {
+	let is_json = if year.ends_with(".json") {
+		year.truncate(year.len() - 5);
+		true
+	} else {
+		false
+	};
+
+	let year: i32 = year.parse().map_err(|err| {
+		println!("Could not parse year: {:?} ({}:{})", err, file!(), line!());
+		Status::NotFound
+	})?;
+
+	let (mut leaderboard, secs_til_next_update, stale) = get_leaderboard(
+		year,
+		&conn,
+		&redis,
+		settings,
+		leaderboard_source,
+		identity_provider,
+	)
+	.await?;
+
+	if is_json {
+		Ok(JsonOrTemplateLeaderboard::json(leaderboard))
+	} else {
+		let join_code = get_join_code(year, &conn, &redis, cookies, identity_provider)
+			.await
+			.ok()
+			.flatten();
+
+		let context = create_base_context(
+			LeaderboardContext {
+				year,
+				description: "This is (almost) the same leaderboard as presented on AoC, whoever \
+				              completes each puzzle first each day scores the highest."
+					.into(),
+				value_width: 6,
+				join_code,
+				profiles_unavailable: leaderboard.iter().any(|lr| lr.profile_unavailable),
+				source_unavailable: stale,
+				leaderboard: leaderboard.drain(..).map(From::from).collect(),
+				next_update: LeaderboardContext::format_next_update(secs_til_next_update),
+			},
+			cookies,
+			identity_provider,
+		)
+		.await;
+		Ok(JsonOrTemplateLeaderboard::Template(Template::render(
+			"leaderboard",
+			context,
+		)))
+	}
+}
+
+#[get("/leaderboard/<year>/splits.json")]
+async fn get_leaderboard_year_splits_json(
+	year: i32,
+	conn: DbConn,
+	redis: RedisConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<Json<Vec<LeaderboardSplitsResponse>>, Status> {
+	get_leaderboard_splits(
+		year,
+		&conn,
+		&redis,
+		settings,
+		leaderboard_source,
+		identity_provider,
+	)
+	.await
+	.map(|(leaderboard, ..)| Json(leaderboard))
+}
+
+#[get("/leaderboard/<year>/splits")]
+async fn get_leaderboard_year_splits(
+	year: i32,
+	conn: DbConn,
+	redis: RedisConn,
+	settings: &Settings,
+	cookies: &CookieJar<'_>,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<Template, Status> {
+	let (mut leaderboard, secs_til_next_update, stale) = get_leaderboard_splits(
+		year,
+		&conn,
+		&redis,
+		settings,
+		leaderboard_source,
+		identity_provider,
+	)
+	.await?;
+
+	let join_code = get_join_code(year, &conn, &redis, cookies, identity_provider)
+		.await
+		.ok()
+		.flatten();
+
+	let context = create_base_context(
+		LeaderboardContext {
+			year,
+			description: "This leaderboard ranks players according to split time, the player with \
+			              the shortest split each day earns the most points. If you like sleep, \
+			              this might be the leaderboard for you."
+				.into(),
+			value_width: 6,
+			join_code,
+			profiles_unavailable: leaderboard.iter().any(|lr| lr.profile_unavailable),
+			source_unavailable: stale,
+			leaderboard: leaderboard.drain(..).map(From::from).collect(),
+			next_update: LeaderboardContext::format_next_update(secs_til_next_update),
+		},
+		cookies,
+		identity_provider,
+	)
+	.await;
+	Ok(Template::render("leaderboard", context))
+}
+
+#[get("/leaderboard/<year>/languages.json")]
+async fn get_leaderboard_year_languages_json(
+	year: i32,
+	conn: DbConn,
+	redis: RedisConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+	code_hosts: &CodeHosts,
+) -> Result<Json<Vec<LeaderboardLanguagesResponse>>, Status> {
+	get_leaderboard_languages(
+		year,
+		&conn,
+		&redis,
+		settings,
+		leaderboard_source,
+		identity_provider,
+		code_hosts,
+	)
+	.await
+	.map(|(leaderboard, ..)| Json(leaderboard))
+}
+
+#[get("/leaderboard/<year>/languages")]
+#[allow(clippy::too_many_arguments)]
+async fn get_leaderboard_year_languages(
+	year: i32,
+	conn: DbConn,
+	redis: RedisConn,
+	settings: &Settings,
+	cookies: &CookieJar<'_>,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+	code_hosts: &CodeHosts,
+) -> Result<Template, Status> {
+	let (mut leaderboard, secs_til_next_update, stale) = get_leaderboard_languages(
+		year,
+		&conn,
+		&redis,
+		settings,
+		leaderboard_source,
+		identity_provider,
+		code_hosts,
+	)
+	.await?;
+
+	let join_code = get_join_code(year, &conn, &redis, cookies, identity_provider)
+		.await
+		.ok()
+		.flatten();
+
+	let context = create_base_context(
+		LeaderboardContext {
+			year,
+			description: "If you want to learn a new language, or a few, AoC is the perfect time \
+			              to do so. If you list your repo in settings you will compete on this \
+			              leaderboard with the number of languages you've solved puzzles in."
+				.into(),
+			value_width: 3,
+			join_code,
+			profiles_unavailable: leaderboard.iter().any(|lr| lr.profile_unavailable),
+			source_unavailable: stale,
+			leaderboard: leaderboard.drain(..).map(From::from).collect(),
+			next_update: LeaderboardContext::format_next_update(secs_til_next_update),
+		},
+		cookies,
+		identity_provider,
+	)
+	.await;
+	Ok(Template::render("leaderboard", context))
+}
+
+#[get("/leaderboard/<year>/commits.json")]
+async fn get_leaderboard_year_commits_json(
+	year: i32,
+	conn: DbConn,
+	redis: RedisConn,
+	settings: &Settings,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+	code_hosts: &CodeHosts,
+) -> Result<Json<Vec<LeaderboardCommitsResponse>>, Status> {
+	get_leaderboard_commits(
+		year,
+		&conn,
+		&redis,
+		settings,
+		leaderboard_source,
+		identity_provider,
+		code_hosts,
+	)
+	.await
+	.map(|(leaderboard, ..)| Json(leaderboard))
+}
+
+#[get("/leaderboard/<year>/commits")]
+#[allow(clippy::too_many_arguments)]
+async fn get_leaderboard_year_commits(
+	year: i32,
+	conn: DbConn,
+	redis: RedisConn,
+	settings: &Settings,
+	cookies: &CookieJar<'_>,
+	leaderboard_source: &dyn LeaderboardSource,
+	identity_provider: &dyn IdentityProvider,
+	code_hosts: &CodeHosts,
+) -> Result<Template, Status> {
+	let (mut leaderboard, secs_til_next_update, stale) = get_leaderboard_commits(
+		year,
+		&conn,
+		&redis,
+		settings,
+		leaderboard_source,
+		identity_provider,
+		code_hosts,
+	)
+	.await?;
+
+	let join_code = get_join_code(year, &conn, &redis, cookies, identity_provider)
+		.await
+		.ok()
+		.flatten();
+
+	let context = create_base_context(
+		LeaderboardContext {
+			year,
+			description: format!(
+				"Publishing your solutions lets others learn from them. If you list your repo in \
+				 settings you will compete on this leaderboard with the number of days you \
+				 committed your solution within {} hours of the puzzle unlocking.",
+				settings.commit_window() / (60 * 60)
+			),
+			value_width: 2,
+			join_code,
+			profiles_unavailable: leaderboard.iter().any(|lr| lr.profile_unavailable),
+			source_unavailable: stale,
+			leaderboard: leaderboard.drain(..).map(From::from).collect(),
+			next_update: LeaderboardContext::format_next_update(secs_til_next_update),
+		},
+		cookies,
+		identity_provider,
+	)
+	.await;
+	Ok(Template::render("leaderboard", context))
+}
+
+#[get("/callback?<code>&<state>")]
+async fn callback(
+	code: String,
+	state: Option<String>,
+	redis: RedisConn,
+	settings: &Settings,
+	cookies: &CookieJar<'_>,
+	identity_provider: &dyn IdentityProvider,
+) -> Result<Redirect, Status> {
+	let access_token = identity_provider
+		.get_token(code.clone())
+		.await
+		.map_err(|e| {
+			println!(
+				"Could not get token for code {} ({}:{})\n\t{:?}",
+				code,
+				file!(),
+				line!(),
+				e
+			);
+			Status::Unauthorized
+		})?;
+	let user = identity_provider.get_me(&access_token).await.map_err(|e| {
+		println!(
+			"Could not get \"me\" info with access token {} ({}:{})\n\t{:?}",
+			access_token,
+			file!(),
+			line!(),
+			e
+		);
+		Status::Unauthorized
+	})?;
+	remember_profile(&user, &redis, settings).await;
+	cookies.add(
+		Cookie::build(identity::cookie(), access_token)
+			.path("/")
+			.finish(),
+	);
+	Ok(Redirect::to(
+		state
+			.and_then(|s| Origin::parse_owned(s).ok())
+			.unwrap_or(uri!(index)),
+	))
+}
+
+#[get("/callback?<error>&<error_description>", rank = 2)]
+async fn callback_error(error: String, error_description: Option<String>) -> String {
+	match error_description {
+		Some(desc) => format!("{}\n{}", error, desc),
+		None => error,
+	}
+}
+
+#[get("/")]
+async fn index(cookies: &CookieJar<'_>, identity_provider: &dyn IdentityProvider) -> Template {
+	let context = create_base_context((), cookies, identity_provider).await;
+	Template::render("index", context)
+}
+
+#[get("/about")]
+async fn about(cookies: &CookieJar<'_>, identity_provider: &dyn IdentityProvider) -> Template {
+	let context = create_base_context((), cookies, identity_provider).await;
+	Template::render("about", context)
+}
+
+#[get("/settings")]
+async fn settings(
+	conn: DbConn,
+	user: Authorized,
+	flash: Option<FlashMessage<'_>>,
+	cookies: &CookieJar<'_>,
+	identity_provider: &dyn IdentityProvider,
+	leaderboard_source: &dyn LeaderboardSource,
+) -> Result<Template, Status> {
+	let aoc_id = get_aoc_id(&conn, &user)
+		.await
+		.map(|aoc_id| Some(aoc_id.aoc_id))
+		.or_else(|e| {
+			if e.code == Status::NotFound.code {
+				Ok(None)
+			} else {
+				Err(e)
+			}
+		})?;
+	// Every year is listed, newest first, so past years can be joined as long
+	// as their registration is open.
+	let mut participations = get_participations(&conn, &user).await?;
+	let mut years: Vec<_> = get_years(&conn)
+		.await?
+		.into_iter()
+		.map(|y| {
+			let participation = participations
+				.iter()
+				.position(|p| p.year == y.year)
+				.map(|i| participations.swap_remove(i));
+			ParticipationContext {
+				year: y.year,
+				is_participating: participation.is_some(),
+				repo: participation.and_then(|p| p.repo),
+				registration_open: y.registration_open,
+			}
+		})
+		.collect();
+	years.sort_by_key(|y| Reverse(y.year));
+	let api_tokens = if user.via_token {
+		Vec::new()
+	} else {
+		get_api_tokens(&conn, &user).await?
+	};
+	let admin = if user.roles.is_empty() {
+		None
+	} else {
+		let is_owner = user.has_role(Role::Owner, None);
+		let mut years = Vec::new();
+		for y in get_years(&conn).await? {
+			if user.has_role(Role::Moderator, Some(y.year)) {
+				years.push(AdminYearContext {
+					can_edit: user.has_role(Role::YearAdmin, Some(y.year)),
+					participants: get_year_participants(y.year, &conn, &user).await?,
+					year: y.year,
+					leaderboards: y.leaderboards,
+					scoring: y.scoring,
+					dates: y.dates,
+				});
+			}
+		}
+		let roles = if is_owner {
+			get_roles(&conn, &user).await?
+		} else {
+			Vec::new()
+		};
+		Some(AdminContext {
+			is_owner,
+			aoc_session_expired: leaderboard_source.session_expired(),
+			years,
+			roles,
+		})
+	};
+	let context = create_base_context(
+		SettingsContext {
+			aoc_id,
+			repo_error: flash.map(|flash| flash.message().to_owned()),
+			years,
+			api_tokens,
+			admin,
+		},
+		cookies,
+		identity_provider,
+	)
+	.await;
+	Ok(Template::render("settings", context))
+}
+
+#[get("/config")]
+async fn get_config(
+	settings: &Settings,
+	user: Authorized,
+	cookies: &CookieJar<'_>,
+	identity_provider: &dyn IdentityProvider,
+	leaderboard_source: &dyn LeaderboardSource,
+) -> Result<Template, Status> {
+	let context = create_base_context(
+		ConfigContext {
+			settings: get_settings(settings, &user)?,
+			aoc_session_expired: leaderboard_source.session_expired(),
+		},
+		cookies,
+		identity_provider,
+	)
+	.await;
+	Ok(Template::render("config", context))
+}
+
+pub fn migrate(command: Option<&str>) -> Result<(), String> {
+	let url: String = Config::figment()
+		.extract_inner("databases.sql.url")
+		.map_err(|e| format!("Missing the database URL. ({})", e))?;
+	let conn = PgConnection::establish(&url)
+		.map_err(|e| format!("Could not connect to the database. ({})", e))?;
+
+	match command {
+		Some("run") => {
+			let applied = migrations::run_pending_migrations(&conn).map_err(|e| e.to_string())?;
+			if applied.is_empty() {
+				println!("The database is up to date.");
+			}
+			for migration in applied {
+				println!("Applied migration {}", migration.name);
+			}
+		}
+		Some("revert") => {
+			match migrations::revert_latest_migration(&conn).map_err(|e| e.to_string())? {
+				Some(migration) => println!("Reverted migration {}", migration.name),
+				None => println!("There are no migrations to revert."),
+			}
+		}
+		Some("list") => {
+			let applied = migrations::applied_migrations(&conn).map_err(|e| e.to_string())?;
+			for migration in migrations::MIGRATIONS {
+				let mark = if applied.contains(&migration.version()) {
+					"X"
+				} else {
+					" "
+				};
+				println!("[{}] {}", mark, migration.name);
+			}
+		}
+		_ => return Err("Usage: digit-aoc migrate <run|revert|list>".to_owned()),
+	}
+	Ok(())
+}
+
+pub fn rocket() -> Rocket<Build> {
+	custom(Config::figment())
+}
+
+pub fn custom<T: Provider>(provider: T) -> Rocket<Build> {
+	rocket::custom(provider)
+		.attach(Template::fairing())
+		.attach(DbConn::fairing())
+		.attach(RedisConn::fairing())
+		.attach(config::fairing())
+		.attach(aoc::fairing())
+		.attach(code_host::fairing())
+		.attach(AdHoc::on_liftoff("Initialize the AoC database", |rocket| {
+			Box::pin(async move {
+				if let Err(e) = db::initialize(rocket).await {
+					eprintln!(
+						"Failed to initialize the database connection. ({})\nShutting down...",
+						e
+					);
+					rocket.shutdown().notify();
+					return;
+				}
+
+				let conn = DbConn::get_one(rocket).await;
+				let settings = rocket.state::<Settings>();
+				let leaderboard_source = rocket.state::<Box<dyn LeaderboardSource>>();
+				let code_hosts = rocket.state::<CodeHosts>();
+				let result = match (conn, settings, leaderboard_source, code_hosts) {
+					(Some(conn), Some(settings), Some(leaderboard_source), Some(code_hosts)) => {
+						load_settings(&conn, settings, leaderboard_source.as_ref(), code_hosts)
+							.await
+					}
+					_ => Err(Status::InternalServerError),
+				};
+				if result.is_err() {
+					eprintln!("Failed to load the settings.\nShutting down...");
+					rocket.shutdown().notify();
+				}
+			})
+		}))
+		.mount("/static", FileServer::from("static/"))
+		.mount("/", routes![
+			index,
+			login,
+			login_local,
+			about,
+			settings,
+			get_config,
+			callback,
+			callback_error,
+			get_aoc_id_json,
+			post_aoc_id_json,
+			post_aoc_id,
+			get_years_json,
+			post_years_json,
+			post_years,
+			delete_years_json,
+			delete_years,
+			post_years_scoring_json,
+			post_years_scoring,
+			post_years_dates_json,
+			post_years_dates,
+			delete_year_leaderboards_json,
+			delete_year_leaderboards,
+			get_year_participants_json,
+			delete_year_participants_json,
+			delete_year_participants,
+			get_roles_json,
+			post_roles_json,
+			post_roles,
+			delete_roles_json,
+			delete_roles,
+			get_config_json,
+			post_config_json,
+			post_config,
+			delete_config_json,
+			delete_config,
+			get_participate_json,
+			post_participate_json,
+			post_participate,
+			delete_participate_json,
+			delete_participate,
+			get_api_tokens_json,
+			post_api_tokens_json,
+			post_api_tokens,
+			delete_api_tokens_json,
+			delete_api_tokens,
+			redirect_leaderboard,
+			get_leaderboard_year_json,
+			get_leaderboard_year_splits_json,
+			get_leaderboard_year_splits,
+			get_leaderboard_year_languages_json,
+			get_leaderboard_year_languages,
+			get_leaderboard_year_commits_json,
+			get_leaderboard_year_commits,
+		])
+}
@@ -0,0 +1,179 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Timestamps are seconds since the epoch, code hosts tell times as RFC 3339
+// dates like "2021-12-01T05:00:00Z" or "2021-12-01T06:00:00.000+01:00".
+
+pub fn now() -> i64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |now| now.as_secs() as i64)
+}
+
+// AoC puzzles unlock at midnight EST (UTC-5).
+pub fn puzzle_unlock(year: i32, day: u8) -> i64 {
+	days_from_civil(year as i64, 12, day as u32) * 86400 + 5 * 3600
+}
+
+// Times may also be left out, or given without seconds like forms do, and
+// are in UTC unless they have an offset. Anything out of range, like February
+// 30 or 24:00, is refused.
+pub fn parse(date_time: &str) -> Option<i64> {
+	let (date, time) = date_time
+		.split_once(['T', 't', ' '])
+		.unwrap_or((date_time, "00:00"));
+	let mut date = date.splitn(3, '-').map(|part| part.parse::<u32>().ok());
+	let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+	if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+		return None;
+	}
+
+	let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+		Some(time) => (time, 0),
+		None => match time.rfind(['+', '-']) {
+			Some(i) => {
+				let (time, offset) = time.split_at(i);
+				let (hours, minutes) = offset[1..].split_once(':')?;
+				let (hours, minutes) = (hours.parse::<i64>().ok()?, minutes.parse::<i64>().ok()?);
+				if hours >= 24 || minutes >= 60 {
+					return None;
+				}
+				let secs = hours * 3600 + minutes * 60;
+				(time, if offset.starts_with('-') { -secs } else { secs })
+			}
+			None => (time, 0),
+		},
+	};
+	// Fractions of seconds don't matter.
+	let time = time.split_once('.').map_or(time, |(time, _)| time);
+	let mut time = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+	let (hours, minutes) = (time.next()??, time.next()??);
+	let seconds = time.next().unwrap_or(Some(0))?;
+	// A leap second is written as the 60th second.
+	if hours >= 24 || minutes >= 60 || seconds > 60 {
+		return None;
+	}
+
+	days_from_civil(year as i64, month, day)
+		.checked_mul(86400)?
+		.checked_add(hours * 3600 + minutes * 60 + seconds)?
+		.checked_sub(offset)
+}
+
+pub fn format(ts: i64) -> String {
+	let (year, month, day) = civil_from_days(ts.div_euclid(86400));
+	let secs = ts.rem_euclid(86400);
+	format!(
+		"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+		year,
+		month,
+		day,
+		secs / 3600,
+		secs / 60 % 60,
+		secs % 60
+	)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+	match month {
+		2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+			29
+		}
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	}
+}
+
+// Days since the epoch of a date in the proleptic Gregorian calendar, counting
+// years from March so that leap days come last.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year.rem_euclid(400);
+	let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+	let days = days + 719468;
+	let era = days.div_euclid(146097);
+	let day_of_era = days.rem_euclid(146097);
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+	let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+	let year = year_of_era + era * 400;
+	(if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_dates_with_offsets() {
+		assert_eq!(parse("2020-12-01T05:00:00Z"), Some(1_606_798_800));
+		assert_eq!(parse("2020-12-01t05:00:00z"), Some(1_606_798_800));
+		assert_eq!(parse("2020-12-01T00:00:00-05:00"), Some(1_606_798_800));
+		assert_eq!(parse("2020-12-01T06:00:00.123+01:00"), Some(1_606_798_800));
+		assert_eq!(parse("2020-12-01 05:00"), Some(1_606_798_800));
+		assert_eq!(parse("1969-12-31T23:59:59Z"), Some(-1));
+	}
+
+	#[test]
+	fn parses_dates_without_times() {
+		assert_eq!(parse("2020-12-01"), Some(1_606_780_800));
+		assert_eq!(parse("1970-01-01"), Some(0));
+	}
+
+	#[test]
+	fn parses_leap_days_of_leap_years_only() {
+		assert_eq!(parse("2020-02-29"), Some(1_582_934_400));
+		assert_eq!(parse("2000-02-29"), Some(951_782_400));
+		assert_eq!(parse("2021-02-29"), None);
+		assert_eq!(parse("1900-02-29"), None);
+		assert_eq!(parse("2021-04-31"), None);
+	}
+
+	#[test]
+	fn refuses_values_out_of_range() {
+		for date_time in [
+			"2020-00-01",
+			"2020-13-01",
+			"2020-12-00",
+			"2020-12-32",
+			"2020-12-01T24:00",
+			"2020-12-01T12:60",
+			"2020-12-01T12:00:61",
+			"2020-12-01T12:00+24:00",
+			"2020-12-01T12:00+01:60",
+			"2020-12-01T",
+			"2020-12",
+			"soon",
+		] {
+			assert_eq!(parse(date_time), None, "{}", date_time);
+		}
+		assert_eq!(parse("2016-12-31T23:59:60Z"), Some(1_483_228_800));
+	}
+
+	#[test]
+	fn formats_as_utc() {
+		assert_eq!(format(1_606_798_800), "2020-12-01T05:00:00Z");
+		assert_eq!(format(0), "1970-01-01T00:00:00Z");
+		assert_eq!(format(-1), "1969-12-31T23:59:59Z");
+		assert_eq!(format(951_782_400), "2000-02-29T00:00:00Z");
+		for ts in [0, 1_582_934_399, 1_606_798_800, 4_102_444_800] {
+			assert_eq!(parse(&format(ts)), Some(ts));
+		}
+	}
+
+	#[test]
+	fn puzzles_unlock_at_midnight_est() {
+		assert_eq!(puzzle_unlock(2020, 1), 1_606_798_800);
+		assert_eq!(puzzle_unlock(2020, 25), 1_606_798_800 + 24 * 86400);
+		assert_eq!(format(puzzle_unlock(2015, 1)), "2015-12-01T05:00:00Z");
+	}
+}
@@ -0,0 +1,325 @@
+mod common;
+
+use common::{
+	bearer,
+	create_year,
+	delete_json,
+	get_json,
+	json_body,
+	location,
+	post_form,
+	post_json,
+	session,
+};
+use rocket::{
+	http::{ContentType, Status},
+	serde::json::json,
+};
+
+#[rocket::async_test]
+async fn aoc_id_can_be_set_and_read() {
+	let client = common::client().await;
+
+	let (status, _) = get_json(&client, "/aoc-id.json", "accounts-aoc").await;
+	assert_eq!(status, Status::NotFound);
+
+	let status = post_json(
+		&client,
+		"/aoc-id.json",
+		"accounts-aoc",
+		json!({ "aocId": "1000001" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let (status, body) = get_json(&client, "/aoc-id.json", "accounts-aoc").await;
+	assert_eq!(status, Status::Ok);
+	assert_eq!(body, Some(json!({ "aocId": "1000001" })));
+
+	let response = post_form(&client, "/aoc-id", "accounts-aoc", "aocId=1000002").await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let (_, body) = get_json(&client, "/aoc-id.json", "accounts-aoc").await;
+	assert_eq!(body, Some(json!({ "aocId": "1000002" })));
+}
+
+#[rocket::async_test]
+async fn aoc_id_requires_a_session() {
+	let client = common::client().await;
+
+	let response = client.get("/aoc-id.json").dispatch().await;
+	assert_eq!(response.status(), Status::Unauthorized);
+	let status = post_json(
+		&client,
+		"/aoc-id.json",
+		"ghost-aoc",
+		json!({ "aocId": "1000003" }),
+	)
+	.await;
+	assert_eq!(status, Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn participation_can_be_joined_updated_and_left() {
+	let client = common::client().await;
+	create_year(&client, 2101, "2101", &[]).await;
+	let cid = "accounts-participate";
+
+	assert_eq!(
+		post_json(&client, "/aoc-id.json", cid, json!({ "aocId": "1000004" })).await,
+		Status::Ok
+	);
+	let (status, body) = get_json(&client, "/participate.json", cid).await;
+	assert_eq!(status, Status::Ok);
+	assert_eq!(body, Some(json!([])));
+
+	let status = post_json(
+		&client,
+		"/participate.json",
+		cid,
+		// Repos used to be called "github", which is still what they are
+		// called in responses.
+		json!({ "year": 2101, "github": "someone/rust" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let (_, body) = get_json(&client, "/participate.json", cid).await;
+	assert_eq!(
+		body,
+		Some(json!([{ "year": 2101, "github": "someone/rust" }]))
+	);
+
+	let response = post_form(&client, "/participate", cid, "year=2101").await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let (_, body) = get_json(&client, "/participate.json", cid).await;
+	assert_eq!(body, Some(json!([{ "year": 2101, "github": null }])));
+
+	let status = delete_json(&client, "/participate.json", cid, json!({ "year": 2101 })).await;
+	assert_eq!(status, Status::Ok);
+	let (_, body) = get_json(&client, "/participate.json", cid).await;
+	assert_eq!(body, Some(json!([])));
+	let status = delete_json(&client, "/participate.json", cid, json!({ "year": 2101 })).await;
+	assert_eq!(status, Status::NotFound);
+
+	let response = post_form(&client, "/participate", cid, "year=2101").await;
+	assert_eq!(response.status(), Status::SeeOther);
+	let response = post_form(&client, "/participate-delete", cid, "year=2101").await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let (_, body) = get_json(&client, "/participate.json", cid).await;
+	assert_eq!(body, Some(json!([])));
+}
+
+#[rocket::async_test]
+async fn settings_offer_every_year() {
+	let client = common::client().await;
+	create_year(&client, 2103, "2103", &[]).await;
+	create_year(&client, 2104, "2104", &[]).await;
+	let cid = "accounts-years";
+	assert_eq!(
+		post_json(&client, "/aoc-id.json", cid, json!({ "aocId": "1000007" })).await,
+		Status::Ok
+	);
+
+	// Joining an earlier year doesn't need the later one.
+	let response = post_form(&client, "/participate", cid, "year=2103").await;
+	assert_eq!(response.status(), Status::SeeOther);
+	let (_, body) = get_json(&client, "/participate.json", cid).await;
+	assert_eq!(body, Some(json!([{ "year": 2103, "github": null }])));
+
+	let body = client
+		.get("/settings")
+		.cookie(session(cid))
+		.dispatch()
+		.await
+		.into_string()
+		.await
+		.unwrap();
+	let newer = body.find("Year: 2104").expect("2104 is not listed.");
+	let older = body.find("Year: 2103").expect("2103 is not listed.");
+	assert!(newer < older);
+	assert_eq!(
+		body.matches("formaction=\"/participate-delete\"").count(),
+		1
+	);
+}
+
+#[rocket::async_test]
+async fn participation_repos_are_checked() {
+	let client = common::client().await;
+	let cid = "accounts-repo";
+	create_year(&client, 2102, "2102", &[]).await;
+	assert_eq!(
+		post_json(&client, "/aoc-id.json", cid, json!({ "aocId": "1000006" })).await,
+		Status::Ok
+	);
+	let client = &client;
+	let repo = |repo: String| async move {
+		let status = post_json(
+			client,
+			"/participate.json",
+			cid,
+			json!({ "year": 2102, "repo": repo }),
+		)
+		.await;
+		let (_, body) = get_json(client, "/participate.json", cid).await;
+		(status, body.unwrap()[0]["github"].clone())
+	};
+
+	// Links and clone URLs are stored as the repo itself.
+	let gitlab = format!("{}/gitlab/someone/polyglot", common::mock_url());
+	for (given, stored) in [
+		(
+			"https://github.com/someone/rust.git".to_owned(),
+			json!("someone/rust"),
+		),
+		(
+			"git@github.com:someone/polyglot.git".to_owned(),
+			json!("someone/polyglot"),
+		),
+		(
+			"https://github.com/someone/rust/tree/main/src".to_owned(),
+			json!("someone/rust"),
+		),
+		(format!("{}/-/tree/main/", gitlab), json!(gitlab)),
+		(" ".to_owned(), json!(null)),
+	] {
+		assert_eq!(repo(given.clone()).await, (Status::Ok, stored), "{}", given);
+	}
+
+	for given in [
+		"someone/missing",
+		"ghost/rust",
+		"someone/secret",
+		"not a repo",
+		"https://github.com/someone",
+		"https://git.example.com/someone/rust",
+	] {
+		let (status, stored) = repo(given.to_owned()).await;
+		assert_eq!(status, Status::BadRequest, "{}", given);
+		assert_eq!(stored, json!(null), "{}", given);
+	}
+
+	// Forms show what's wrong on the settings page.
+	let response = post_form(
+		client,
+		"/participate",
+		cid,
+		"year=2102&repo=someone%2Fsecret",
+	)
+	.await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let flash = response
+		.cookies()
+		.get("_flash")
+		.expect("No message for the settings page.")
+		.clone()
+		.into_owned();
+	let body = client
+		.get("/settings")
+		.cookie(session(cid))
+		.cookie(flash)
+		.dispatch()
+		.await
+		.into_string()
+		.await
+		.unwrap();
+	assert!(body.contains("The repo is private"));
+}
+
+#[rocket::async_test]
+async fn api_tokens_authenticate_json_endpoints() {
+	let client = common::client().await;
+	let cid = "accounts-tokens";
+
+	let response = client
+		.post("/api-tokens.json")
+		.cookie(common::session(cid))
+		.json(&json!({ "name": "bot" }))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	let created = json_body(response).await.unwrap();
+	let token = created["token"].as_str().unwrap().to_owned();
+	assert!(token.starts_with("aod_"));
+
+	let status = post_json(&client, "/api-tokens.json", cid, json!({ "name": "bot" })).await;
+	assert_eq!(status, Status::Conflict);
+
+	let (status, body) = get_json(&client, "/api-tokens.json", cid).await;
+	assert_eq!(status, Status::Ok);
+	assert_eq!(body, Some(json!([{ "id": created["id"], "name": "bot" }])));
+
+	let response = client
+		.post("/aoc-id.json")
+		.header(bearer(&token))
+		.json(&json!({ "aocId": "1000005" }))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	let response = client
+		.get("/aoc-id.json")
+		.header(bearer(&token))
+		.dispatch()
+		.await;
+	assert_eq!(
+		json_body(response).await,
+		Some(json!({ "aocId": "1000005" }))
+	);
+
+	// Tokens may not be used to manage tokens.
+	let response = client
+		.get("/api-tokens.json")
+		.header(bearer(&token))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Forbidden);
+
+	let response = client
+		.get("/aoc-id.json")
+		.header(bearer("aod_invalid"))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Unauthorized);
+
+	let status = delete_json(
+		&client,
+		"/api-tokens.json",
+		cid,
+		json!({ "id": created["id"] }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let response = client
+		.get("/aoc-id.json")
+		.header(bearer(&token))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn api_tokens_can_be_managed_from_settings() {
+	let client = common::client().await;
+	let cid = "accounts-token-forms";
+
+	let response = client
+		.post("/api-tokens")
+		.cookie(common::session(cid))
+		.header(ContentType::Form)
+		.body("name=script")
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	assert!(response.into_string().await.unwrap().contains("aod_"));
+
+	let (_, body) = get_json(&client, "/api-tokens.json", cid).await;
+	let id = body.unwrap()[0]["id"].as_i64().unwrap();
+
+	let response = post_form(&client, "/api-tokens-delete", cid, &format!("id={}", id)).await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let (_, body) = get_json(&client, "/api-tokens.json", cid).await;
+	assert_eq!(body, Some(json!([])));
+}
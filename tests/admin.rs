@@ -0,0 +1,540 @@
+mod common;
+
+use common::{
+	bearer,
+	create_year,
+	delete_json,
+	get_json,
+	join,
+	json_body,
+	location,
+	mock,
+	post_form,
+	post_json,
+	session,
+};
+use rocket::{
+	http::Status,
+	serde::json::{json, Value},
+};
+
+fn contains_year(years: &Option<Value>, year: i32, leaderboards: &[&str]) -> bool {
+	years
+		.as_ref()
+		.and_then(Value::as_array)
+		.is_some_and(|years| {
+			years
+				.iter()
+				.any(|y| y["year"] == year && y["leaderboards"] == json!(leaderboards))
+		})
+}
+
+#[rocket::async_test]
+async fn owners_manage_years() {
+	let client = common::client().await;
+
+	let status = post_json(
+		&client,
+		"/years.json",
+		"owner-years",
+		json!({ "year": 2201, "leaderboard": "2201-abc" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let response = client.get("/years.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	assert!(contains_year(&json_body(response).await, 2201, &["2201"]));
+
+	let response = post_form(
+		&client,
+		"/years",
+		"owner-years",
+		"year=2201&leaderboard=2201-def",
+	)
+	.await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let (_, years) = get_json(&client, "/years.json", "owner-years").await;
+	assert!(contains_year(&years, 2201, &["2201"]));
+
+	// A year may use several leaderboards.
+	let status = post_json(
+		&client,
+		"/years.json",
+		"owner-years",
+		json!({ "year": 2201, "leaderboard": "2209-ghi" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let (_, years) = get_json(&client, "/years.json", "owner-years").await;
+	assert!(contains_year(&years, 2201, &["2201", "2209"]));
+	let status = delete_json(
+		&client,
+		"/years/leaderboards.json",
+		"owner-years",
+		json!({ "year": 2201, "leaderboard": "2209" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let status = delete_json(
+		&client,
+		"/years/leaderboards.json",
+		"owner-years",
+		json!({ "year": 2201, "leaderboard": "2209" }),
+	)
+	.await;
+	assert_eq!(status, Status::NotFound);
+	let (_, years) = get_json(&client, "/years.json", "owner-years").await;
+	assert!(contains_year(&years, 2201, &["2201"]));
+
+	let status = post_json(
+		&client,
+		"/years.json",
+		"owner-years",
+		json!({ "year": 2201, "leaderboard": "not a leaderboard" }),
+	)
+	.await;
+	assert_eq!(status, Status::BadRequest);
+
+	let status = delete_json(
+		&client,
+		"/years.json",
+		"owner-years",
+		json!({ "year": 2201 }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let (_, years) = get_json(&client, "/years.json", "owner-years").await;
+	assert!(!contains_year(&years, 2201, &["2201"]));
+	let status = delete_json(
+		&client,
+		"/years.json",
+		"owner-years",
+		json!({ "year": 2201 }),
+	)
+	.await;
+	assert_eq!(status, Status::NotFound);
+
+	create_year(&client, 2202, "2202", &[]).await;
+	let response = post_form(&client, "/years-delete", "owner-years", "year=2202").await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let (_, years) = get_json(&client, "/years.json", "owner-years").await;
+	assert!(!contains_year(&years, 2202, &["2202"]));
+}
+
+#[rocket::async_test]
+async fn years_are_owner_only() {
+	let client = common::client().await;
+	create_year(&client, 2203, "2203", &[]).await;
+
+	let response = client
+		.post("/years.json")
+		.json(&json!({ "year": 2204, "leaderboard": "2204-abc" }))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Unauthorized);
+
+	let status = post_json(
+		&client,
+		"/years.json",
+		"admin-years",
+		json!({ "year": 2204, "leaderboard": "2204-abc" }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+	let status = post_json(
+		&client,
+		"/years.json",
+		"admin-years",
+		json!({ "year": 2203, "leaderboard": "2203-abc" }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+	let status = delete_json(
+		&client,
+		"/years.json",
+		"admin-years",
+		json!({ "year": 2203 }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+	let response = post_form(&client, "/years-delete", "admin-years", "year=2203").await;
+	assert_eq!(response.status(), Status::Forbidden);
+	let response = post_form(
+		&client,
+		"/years/leaderboards-delete",
+		"admin-years",
+		"year=2203&leaderboard=2203",
+	)
+	.await;
+	assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[rocket::async_test]
+async fn moderators_manage_participants() {
+	let client = common::client().await;
+	create_year(&client, 2205, "2205", &["admin-mod-a", "admin-mod-b"]).await;
+
+	let (status, _) = get_json(&client, "/years/2205/participants.json", "admin-mod").await;
+	assert_eq!(status, Status::Forbidden);
+	let status = post_json(
+		&client,
+		"/roles.json",
+		"owner-mod",
+		json!({ "subject": "admin-mod", "role": "moderator", "year": 2205 }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+
+	let (status, participants) =
+		get_json(&client, "/years/2205/participants.json", "admin-mod").await;
+	assert_eq!(status, Status::Ok);
+	assert_eq!(
+		participants,
+		Some(json!([
+			{ "cid": "admin-mod-a", "repo": null },
+			{ "cid": "admin-mod-b", "repo": null },
+		]))
+	);
+
+	let status = delete_json(
+		&client,
+		"/years/participants.json",
+		"admin-mod",
+		json!({ "year": 2205, "cid": "admin-mod-a" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let response = post_form(
+		&client,
+		"/years/participants-delete",
+		"admin-mod",
+		"year=2205&cid=admin-mod-b",
+	)
+	.await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let (_, participants) = get_json(&client, "/years/2205/participants.json", "admin-mod").await;
+	assert_eq!(participants, Some(json!([])));
+
+	// Moderating one year gives no rights over another.
+	create_year(&client, 2206, "2206", &[]).await;
+	join(&client, 2206, "admin-mod-c", "2206901", None).await;
+	let (status, _) = get_json(&client, "/years/2206/participants.json", "admin-mod").await;
+	assert_eq!(status, Status::Forbidden);
+	let status = delete_json(
+		&client,
+		"/years/participants.json",
+		"admin-mod",
+		json!({ "year": 2206, "cid": "admin-mod-c" }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+}
+
+#[rocket::async_test]
+async fn registration_follows_year_dates() {
+	let client = common::client().await;
+	create_year(&client, 2208, "2208", &["admin-dates-a"]).await;
+	assert_eq!(
+		post_json(
+			&client,
+			"/aoc-id.json",
+			"admin-dates-b",
+			json!({ "aocId": "1000008" })
+		)
+		.await,
+		Status::Ok
+	);
+	let dates = |years: &Option<Value>| {
+		years
+			.as_ref()
+			.and_then(Value::as_array)
+			.and_then(|years| years.iter().find(|y| y["year"] == 2208))
+			.map(|y| (y["dates"].clone(), y["registrationOpen"].clone()))
+	};
+	let (_, years) = get_json(&client, "/years.json", "admin-dates-a").await;
+	assert_eq!(
+		dates(&years),
+		Some((json!({ "startsAt": "2208-12-01T05:00:00Z" }), json!(true)))
+	);
+
+	let status = post_json(
+		&client,
+		"/years/dates.json",
+		"admin-dates-a",
+		json!({ "year": 2208, "registrationCloses": "2020-01-01" }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+	let status = post_json(
+		&client,
+		"/years/dates.json",
+		"owner-dates",
+		json!({
+			"year": 2208,
+			"startsAt": "2208-11-30T18:00:00-05:00",
+			"registrationOpens": "2019-11-01T00:00",
+			"registrationCloses": "2020-01-01",
+		}),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let (_, years) = get_json(&client, "/years.json", "admin-dates-a").await;
+	assert_eq!(
+		dates(&years),
+		Some((
+			json!({
+				"startsAt": "2208-11-30T23:00:00Z",
+				"registrationOpens": "2019-11-01T00:00:00Z",
+				"registrationCloses": "2020-01-01T00:00:00Z",
+			}),
+			json!(false)
+		))
+	);
+
+	// Registration has closed, those already taking part may still change
+	// their repo.
+	let join = |cid: &'static str| {
+		let client = &client;
+		async move {
+			post_json(
+				client,
+				"/participate.json",
+				cid,
+				json!({ "year": 2208, "repo": null }),
+			)
+			.await
+		}
+	};
+	assert_eq!(join("admin-dates-a").await, Status::Ok);
+	assert_eq!(join("admin-dates-b").await, Status::Forbidden);
+	// The repo isn't checked once registration has closed.
+	let status = post_json(
+		&client,
+		"/participate.json",
+		"admin-dates-b",
+		json!({ "year": 2208, "repo": "someone/closed-registration" }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+	assert!(!mock::looked_up("someone/closed-registration"));
+	let response = post_form(&client, "/participate", "admin-dates-b", "year=2208").await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+
+	for dates in [
+		json!({ "year": 2208, "startsAt": "December" }),
+		json!({ "year": 2208, "registrationOpens": "2208-11-15T00:00:00+25" }),
+		json!({
+			"year": 2208,
+			"registrationOpens": "2208-12-01",
+			"registrationCloses": "2208-11-01",
+		}),
+	] {
+		let status = post_json(&client, "/years/dates.json", "owner-dates", dates.clone()).await;
+		assert_eq!(status, Status::BadRequest, "{}", dates);
+	}
+	let status = post_json(
+		&client,
+		"/years/dates.json",
+		"owner-dates",
+		json!({ "year": 2209 }),
+	)
+	.await;
+	assert_eq!(status, Status::NotFound);
+
+	// Forms clear dates left empty.
+	let response = post_form(
+		&client,
+		"/years/dates",
+		"owner-dates",
+		"year=2208&startsAt=&registrationOpens=&registrationCloses=",
+	)
+	.await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	assert_eq!(join("admin-dates-b").await, Status::Ok);
+}
+
+// Only moderating is allowed with an API token, the other roles need a
+// signed in session.
+#[rocket::async_test]
+async fn api_tokens_only_moderate() {
+	let client = common::client().await;
+	create_year(&client, 2210, "2210", &["admin-token-a"]).await;
+	let cid = "owner-token";
+	let response = client
+		.post("/api-tokens.json")
+		.cookie(session(cid))
+		.json(&json!({ "name": "admin" }))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	let token = json_body(response).await.unwrap()["token"]
+		.as_str()
+		.unwrap()
+		.to_owned();
+
+	let response = client
+		.get("/years/2210/participants.json")
+		.header(bearer(&token))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	for (uri, body) in [
+		(
+			"/years.json",
+			json!({ "year": 2210, "leaderboard": "2210-abc" }),
+		),
+		(
+			"/years/scoring.json",
+			json!({ "year": 2210, "points": "stars" }),
+		),
+		(
+			"/roles.json",
+			json!({ "subject": "admin-token-a", "role": "owner" }),
+		),
+		(
+			"/config.json",
+			json!({ "key": "profile-cache-time", "value": "1" }),
+		),
+	] {
+		let response = client
+			.post(uri)
+			.header(bearer(&token))
+			.json(&body)
+			.dispatch()
+			.await;
+		assert_eq!(response.status(), Status::Forbidden, "{}", uri);
+	}
+	let response = client
+		.get("/config.json")
+		.header(bearer(&token))
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[rocket::async_test]
+async fn owners_manage_roles() {
+	let client = common::client().await;
+	create_year(&client, 2207, "2207", &[]).await;
+
+	let status = post_json(
+		&client,
+		"/roles.json",
+		"admin-roles",
+		json!({ "subject": "admin-roles", "role": "owner" }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+	let (status, _) = get_json(&client, "/roles.json", "admin-roles").await;
+	assert_eq!(status, Status::Forbidden);
+
+	let status = post_json(
+		&client,
+		"/roles.json",
+		"owner-roles",
+		json!({ "subject": "admin-roles", "role": "year-admin", "year": 2207 }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let status = post_json(
+		&client,
+		"/roles.json",
+		"owner-roles",
+		json!({ "subject": "admin-roles", "role": "year-admin", "year": 2207 }),
+	)
+	.await;
+	assert_eq!(status, Status::Conflict);
+	// Roles without a year can't be given twice either.
+	for expected in [Status::Ok, Status::Conflict] {
+		let status = post_json(
+			&client,
+			"/roles.json",
+			"owner-roles",
+			json!({ "subject": "admin-roles-group", "isGroup": true, "role": "moderator" }),
+		)
+		.await;
+		assert_eq!(status, expected);
+	}
+	let status = post_json(
+		&client,
+		"/roles.json",
+		"owner-roles",
+		json!({ "subject": "admin-roles", "role": "owner", "year": 2207 }),
+	)
+	.await;
+	assert_eq!(status, Status::BadRequest);
+
+	// Year admins may change the leaderboard of their year.
+	let status = post_json(
+		&client,
+		"/years.json",
+		"admin-roles",
+		json!({ "year": 2207, "leaderboard": "2207-xyz" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+
+	let (status, roles) = get_json(&client, "/roles.json", "owner-roles").await;
+	assert_eq!(status, Status::Ok);
+	let role = roles
+		.unwrap()
+		.as_array()
+		.unwrap()
+		.iter()
+		.find(|r| r["subject"] == "admin-roles")
+		.cloned()
+		.unwrap();
+	assert_eq!(role["role"], "year-admin");
+	assert_eq!(role["isGroup"], false);
+	assert_eq!(role["year"], 2207);
+
+	let status = delete_json(
+		&client,
+		"/roles.json",
+		"owner-roles",
+		json!({ "id": role["id"] }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	let status = delete_json(
+		&client,
+		"/roles.json",
+		"owner-roles",
+		json!({ "id": role["id"] }),
+	)
+	.await;
+	assert_eq!(status, Status::NotFound);
+
+	let response = post_form(
+		&client,
+		"/roles",
+		"owner-roles",
+		"subject=admin-roles-group&isGroup=true&role=moderator&year=2207",
+	)
+	.await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	let (_, roles) = get_json(&client, "/roles.json", "owner-roles").await;
+	let id = roles
+		.unwrap()
+		.as_array()
+		.unwrap()
+		.iter()
+		.find(|r| r["subject"] == "admin-roles-group")
+		.map(|r| r["id"].clone())
+		.unwrap();
+	let response = post_form(
+		&client,
+		"/roles-delete",
+		"owner-roles",
+		&format!("id={}", id),
+	)
+	.await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+}
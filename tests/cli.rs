@@ -0,0 +1,78 @@
+mod common;
+
+use common::{get_json, join, mock};
+use digit_aoc::admin::Admin;
+use rocket::{
+	http::Status,
+	serde::json::{json, serde_json, Value},
+};
+
+#[rocket::async_test]
+async fn admin_manages_years_and_aoc_ids() {
+	// Starts the mocks and configures the environment the CLI reads.
+	let client = common::client().await;
+	let admin = Admin::new().await.unwrap();
+
+	admin.add_year(2601, "2601-join2601").await.unwrap();
+	assert!(admin.add_year(2601, "2601-join2601").await.is_err());
+	assert!(admin.set_leaderboard(2602, "2602-join2602").await.is_err());
+	assert!(admin.set_leaderboard(2601, "not a code").await.is_err());
+	admin.set_leaderboard(2601, "2601-again2601").await.unwrap();
+	admin.set_leaderboard(2601, "2602-more2601").await.unwrap();
+	let years = admin.years().await.unwrap();
+	assert!(years.contains(&(2601, vec![
+		"2601-again2601".to_owned(),
+		"2602-more2601".to_owned()
+	])));
+	admin.remove_leaderboard(2601, "2602").await.unwrap();
+	assert!(admin.remove_leaderboard(2601, "2602").await.is_err());
+
+	admin
+		.link_aoc_id("cli-linked", &mock::aoc_id("2601", 1))
+		.await
+		.unwrap();
+	let (status, body) = get_json(&client, "/aoc-id.json", "cli-linked").await;
+	assert_eq!(status, Status::Ok);
+	assert_eq!(body, Some(json!({ "aocId": "260101" })));
+	admin.unlink_aoc_id("cli-linked", false).await.unwrap();
+	let (status, _) = get_json(&client, "/aoc-id.json", "cli-linked").await;
+	assert_eq!(status, Status::NotFound);
+	assert!(admin.unlink_aoc_id("cli-linked", false).await.is_err());
+
+	// Participations are only removed along with the AoC id when forced.
+	join(&client, 2601, "cli-joined", &mock::aoc_id("2601", 2), None).await;
+	assert!(admin.unlink_aoc_id("cli-joined", false).await.is_err());
+	let (status, _) = get_json(&client, "/aoc-id.json", "cli-joined").await;
+	assert_eq!(status, Status::Ok);
+	admin.unlink_aoc_id("cli-joined", true).await.unwrap();
+	let (status, _) = get_json(&client, "/aoc-id.json", "cli-joined").await;
+	assert_eq!(status, Status::NotFound);
+}
+
+#[rocket::async_test]
+async fn admin_exports_and_refreshes_years() {
+	let client = common::client().await;
+	let admin = Admin::new().await.unwrap();
+
+	admin.add_year(2603, "2603-join2603").await.unwrap();
+	join(&client, 2603, "cli-a", &mock::aoc_id("2603", 1), None).await;
+	join(&client, 2603, "cli-b", &mock::aoc_id("2603", 2), None).await;
+
+	let export: Value = serde_json::from_str(&admin.export_json(2603).await.unwrap()).unwrap();
+	assert_eq!(export["year"], 2603);
+	let cids: Vec<_> = export["leaderboard"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|entry| entry["cid"].clone())
+		.collect();
+	assert_eq!(cids, vec![json!("cli-a"), json!("cli-b")]);
+	assert!(export["splits"].is_array());
+	assert!(export["languages"].is_array());
+	assert!(export["commits"].is_array());
+
+	assert_eq!(admin.purge(Some(2603)).await.unwrap(), 4);
+	admin.refresh(2603).await.unwrap();
+	assert_eq!(admin.purge(Some(2603)).await.unwrap(), 4);
+	assert!(admin.refresh(2604).await.is_err());
+}
@@ -0,0 +1,552 @@
+// Every route comes with a URI macro that none of the tests use.
+#![allow(unused_imports)]
+
+use std::{
+	collections::HashSet,
+	sync::Mutex,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use lazy_static::lazy_static;
+use rocket::{
+	form::Form,
+	http::{CookieJar, Header, Status},
+	request::{FromRequest, Outcome},
+	response::content,
+	serde::json::{json, Json, Value},
+	Build,
+	Request,
+	Rocket,
+};
+
+pub const API_KEY: &str = "test-api-key";
+pub const AOC_SESSION: &str = "test-session";
+pub const AOC_CONTACT: &str = "tests@example.com";
+pub const OWNER_GROUP: &str = "digit";
+
+// Users are made up on demand, every cid is known except those starting with
+// "ghost". Users whose cid starts with "owner" are members of the owner group,
+// and those starting with "hidden" can sign in but can't be looked up by cid.
+pub fn user(cid: &str) -> Option<Value> {
+	if cid.starts_with("ghost") {
+		return None;
+	}
+	let groups: Vec<_> = if cid.starts_with("owner") {
+		vec![json!({ "superGroup": { "name": OWNER_GROUP } })]
+	} else {
+		Vec::new()
+	};
+	Some(json!({
+		"cid": cid,
+		"nick": nick(cid),
+		"avatarUrl": format!("https://example.com/{}.png", cid),
+		"groups": groups,
+	}))
+}
+
+pub fn nick(cid: &str) -> String {
+	format!("Nick of {}", cid)
+}
+
+pub fn token(cid: &str) -> String {
+	format!("token-{}", cid)
+}
+
+// Every private leaderboard has three members, with ids made up of the
+// leaderboard id followed by 01, 02 and 03. They are ranked in that order on
+// every leaderboard. Leaderboards whose id starts with 9 are always down.
+pub fn aoc_id(leaderboard: &str, member: u8) -> String {
+	format!("{}{:02}", leaderboard, member)
+}
+
+struct Authorization(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authorization {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		match request.headers().get_one("Authorization") {
+			Some(value) => Outcome::Success(Authorization(value.to_owned())),
+			None => Outcome::Failure((Status::Unauthorized, ())),
+		}
+	}
+}
+
+struct Contact;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Contact {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		match request.headers().get_one("User-Agent") {
+			Some(agent) if agent.contains(AOC_CONTACT) => Outcome::Success(Contact),
+			_ => Outcome::Failure((Status::Forbidden, ())),
+		}
+	}
+}
+
+#[derive(rocket::Responder)]
+enum AocResponse {
+	Json(Json<Value>),
+	Html(content::Html<&'static str>),
+}
+
+#[derive(rocket::FromForm)]
+struct TokenRequest {
+	code: String,
+}
+
+#[rocket::post("/api/oauth/token", data = "<data>")]
+fn gamma_token(data: Form<TokenRequest>) -> Result<Json<Value>, Status> {
+	user(&data.code).ok_or(Status::BadRequest)?;
+	Ok(Json(json!({
+		"access_token": token(&data.code),
+		"token_type": "bearer",
+	})))
+}
+
+#[rocket::get("/api/users/me")]
+fn gamma_me(auth: Authorization) -> Result<Json<Value>, Status> {
+	auth.0
+		.strip_prefix("Bearer token-")
+		.and_then(user)
+		.map(Json)
+		.ok_or(Status::Unauthorized)
+}
+
+#[rocket::get("/api/users/<cid>", rank = 2)]
+fn gamma_user(cid: &str, auth: Authorization) -> Result<Json<Value>, Status> {
+	if auth.0 != format!("pre-shared {}", API_KEY) {
+		return Err(Status::Unauthorized);
+	}
+	if cid.starts_with("hidden") {
+		return Err(Status::NotFound);
+	}
+	user(cid).map(Json).ok_or(Status::NotFound)
+}
+
+#[rocket::get("/<year>/leaderboard/private/view/<leaderboard>")]
+fn aoc_leaderboard(
+	year: i32,
+	leaderboard: &str,
+	cookies: &CookieJar<'_>,
+	_contact: Contact,
+) -> Result<AocResponse, Status> {
+	// Like AoC, answer with a login page rather than an error when the session
+	// is missing or has expired.
+	if cookies.get("session").map(|c| c.value()) != Some(AOC_SESSION) {
+		return Ok(AocResponse::Html(content::Html(
+			"<!DOCTYPE html><html><body>Log in</body></html>",
+		)));
+	}
+	let id = leaderboard
+		.strip_suffix(".json")
+		.filter(|id| id.chars().all(|c| c.is_ascii_digit()))
+		.ok_or(Status::NotFound)?;
+	if id.starts_with('9') {
+		return Err(Status::ServiceUnavailable);
+	}
+
+	// Member 01 solves day 1 quickest, member 02 slower and member 03 only
+	// finishes the first part.
+	let start: u64 = 1_606_798_800;
+	Ok(AocResponse::Json(Json(json!({
+		"event": year.to_string(),
+		"owner_id": aoc_id(id, 1),
+		"members": {
+			aoc_id(id, 1): {
+				"id": aoc_id(id, 1),
+				"local_score": 30,
+				"completion_day_level": {
+					"1": {
+						"1": { "get_star_ts": start + 600 },
+						"2": { "get_star_ts": start + 660 },
+					},
+				},
+			},
+			aoc_id(id, 2): {
+				"id": aoc_id(id, 2),
+				"local_score": 20,
+				"completion_day_level": {
+					"1": {
+						"1": { "get_star_ts": start + 300 },
+						"2": { "get_star_ts": start + 900 },
+					},
+				},
+			},
+			aoc_id(id, 3): {
+				"id": aoc_id(id, 3),
+				"local_score": 10,
+				"completion_day_level": {
+					"1": {
+						"1": { "get_star_ts": start + 1200 },
+					},
+				},
+			},
+		},
+	}))))
+}
+
+pub const GITHUB_TOKEN: &str = "test-github-token";
+pub const GITLAB_TOKEN: &str = "test-gitlab-token";
+pub const GITEA_TOKEN: &str = "test-gitea-token";
+
+// GitLab and Gitea page trees by this many entries.
+const TREE_PAGE: usize = 4;
+
+// The same repos are on every host, with files and directories like GitHub
+// lists them. Owners starting with "ghost" have no repos.
+fn tree(owner: &str, repo: &str) -> Option<Vec<Value>> {
+	let files: &[(&str, u64)] = match repo {
+		// Python is only used on day 2, which nobody has solved.
+		"polyglot" => &[
+			("Makefile", 100),
+			("README.md", 500),
+			("day01/main.rs", 4000),
+			("day01/solve.hs", 2000),
+			("day01/input.txt", 9000),
+			("day01/Makefile", 300),
+			("day01/run.sh", 200),
+			("day02/solve.py", 1000),
+			("docs/index.html", 3000),
+		],
+		"rust" => &[("Cargo.toml", 200), ("src/day1.rs", 4000)],
+		_ => return None,
+	};
+	if owner.starts_with("ghost") {
+		return None;
+	}
+	let mut tree = Vec::new();
+	for &(path, size) in files {
+		if let Some((directory, _)) = path.rsplit_once('/') {
+			tree.push(json!({ "path": directory, "type": "tree" }));
+		}
+		tree.push(json!({ "path": path, "type": "blob", "size": size }));
+	}
+	Some(tree)
+}
+
+// When the commits of a repo that change `path` were made, around the puzzles
+// of 2020, which unlocked at 05:00 UTC. Polyglot's day 1 is committed to an
+// hour and a half after it unlocked. Rust's is two days later, only its
+// Cargo.toml changed on time.
+fn commits(owner: &str, repo: &str, path: Option<&str>) -> Option<Vec<Value>> {
+	let commits: &[(&str, &str)] = match repo {
+		"polyglot" => &[
+			("2020-12-01T01:30:00-05:00", "day01/main.rs"),
+			("2020-11-20T12:00:00Z", "README.md"),
+		],
+		"rust" => &[
+			("2020-12-01T06:00:00Z", "Cargo.toml"),
+			("2020-12-03T07:00:00.000+01:00", "src/day1.rs"),
+		],
+		_ => &[],
+	};
+	tree(owner, repo)?;
+	let changes = |file: &str| {
+		path.is_none_or(|path| file == path || file.starts_with(&format!("{}/", path)))
+	};
+	Some(
+		commits
+			.iter()
+			.filter(|(_, file)| changes(file))
+			.map(|&(date, _)| json!(date))
+			.collect(),
+	)
+}
+
+// GitHub and Gitea list commits alike, and both of them honor `since` and
+// `until`, which these don't.
+fn commit_list(owner: &str, repo: &str, path: Option<&str>) -> Option<Value> {
+	let commits: Vec<_> = commits(owner, repo, path)?
+		.into_iter()
+		.map(|date| json!({ "commit": { "committer": { "date": date } } }))
+		.collect();
+	Some(json!(commits))
+}
+
+// Whether a repo is private, None if there is no such repo. "secret" repos
+// are private.
+fn visibility(owner: &str, repo: &str) -> Option<bool> {
+	match repo {
+		"secret" if !owner.starts_with("ghost") => Some(true),
+		_ => tree(owner, repo).map(|_| false),
+	}
+}
+
+// GitHub is asked with a token rather than the client id and secret, which
+// are set as well.
+struct GitHubToken;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for GitHubToken {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		match request.headers().get_one("Authorization") {
+			Some(value) if value == format!("Bearer {}", GITHUB_TOKEN) => {
+				Outcome::Success(GitHubToken)
+			}
+			_ => Outcome::Failure((Status::Unauthorized, ())),
+		}
+	}
+}
+
+#[rocket::get("/repos/<owner>/<repo>")]
+fn github_repo(owner: &str, repo: &str, _token: GitHubToken) -> Result<Json<Value>, Status> {
+	LOOKED_UP
+		.lock()
+		.unwrap()
+		.insert(format!("{}/{}", owner, repo));
+	let private = visibility(owner, repo).ok_or(Status::NotFound)?;
+	Ok(Json(json!({
+		"full_name": format!("{}/{}", owner, repo),
+		"private": private,
+	})))
+}
+
+#[rocket::get("/repos/<owner>/<repo>/commits?<page>&<path>")]
+fn github_commits(
+	owner: &str,
+	repo: &str,
+	page: usize,
+	path: Option<&str>,
+	_token: GitHubToken,
+) -> Result<Json<Value>, Status> {
+	let commits = commit_list(owner, repo, path).ok_or(Status::NotFound)?;
+	Ok(Json(if page == 1 { commits } else { json!([]) }))
+}
+
+lazy_static! {
+	static ref THROTTLED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+	static ref LOOKED_UP: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+// Whether the GitHub repo was ever looked up.
+pub fn looked_up(repo: &str) -> bool {
+	LOOKED_UP.lock().unwrap().contains(repo)
+}
+
+struct IfNoneMatch(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		Outcome::Success(IfNoneMatch(
+			request
+				.headers()
+				.get_one("If-None-Match")
+				.map(str::to_owned),
+		))
+	}
+}
+
+#[derive(rocket::Responder)]
+struct GitHubResponse {
+	body: (Status, String),
+	etag: Header<'static>,
+	remaining: Header<'static>,
+	reset: Header<'static>,
+}
+
+// Trees are only sent again when they don't match the ETag they're asked
+// with. Repos of owners starting with "throttled" use up the rate limit with
+// the first tree they send, after which they are refused for an hour.
+#[rocket::get("/repos/<owner>/<repo>/git/trees/<_>?recursive=1")]
+fn github_tree(
+	owner: &str,
+	repo: &str,
+	if_none_match: IfNoneMatch,
+	_token: GitHubToken,
+) -> Result<GitHubResponse, Status> {
+	let tree = tree(owner, repo).ok_or(Status::NotFound)?;
+	let etag = format!("\"{}/{}\"", owner, repo);
+	let throttled = owner.starts_with("throttled");
+	let reset = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap()
+		.as_secs()
+		+ 3600;
+	let body = if throttled && !THROTTLED.lock().unwrap().insert(etag.clone()) {
+		(
+			Status::Forbidden,
+			json!({ "message": "API rate limit exceeded" }).to_string(),
+		)
+	} else if if_none_match.0.as_ref() == Some(&etag) {
+		(Status::NotModified, String::new())
+	} else {
+		(
+			Status::Ok,
+			json!({ "sha": "HEAD", "tree": tree, "truncated": false }).to_string(),
+		)
+	};
+	Ok(GitHubResponse {
+		body,
+		etag: Header::new("ETag", etag),
+		remaining: Header::new(
+			"X-RateLimit-Remaining",
+			if throttled { "0" } else { "4999" },
+		),
+		reset: Header::new("X-RateLimit-Reset", reset.to_string()),
+	})
+}
+
+struct GitLabToken;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for GitLabToken {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		match request.headers().get_one("PRIVATE-TOKEN") {
+			Some(GITLAB_TOKEN) => Outcome::Success(GitLabToken),
+			_ => Outcome::Failure((Status::Unauthorized, ())),
+		}
+	}
+}
+
+#[derive(rocket::Responder)]
+struct GitLabPage {
+	entries: Json<Vec<Value>>,
+	next_page: Header<'static>,
+}
+
+// Projects are looked up by their path with the slash encoded.
+#[rocket::get("/api/v4/projects/<project>")]
+fn gitlab_project(project: String, _token: GitLabToken) -> Result<Json<Value>, Status> {
+	let project = project.replace("%2F", "/");
+	let (owner, repo) = project.split_once('/').ok_or(Status::NotFound)?;
+	let private = visibility(owner, repo).ok_or(Status::NotFound)?;
+	Ok(Json(json!({
+		"path_with_namespace": project,
+		"visibility": if private { "private" } else { "public" },
+	})))
+}
+
+#[rocket::get("/api/v4/projects/<project>/repository/tree?recursive=true&<page>")]
+fn gitlab_tree(project: String, page: usize, _token: GitLabToken) -> Result<GitLabPage, Status> {
+	let project = project.replace("%2F", "/");
+	let (owner, repo) = project.split_once('/').ok_or(Status::NotFound)?;
+	let tree = tree(owner, repo).ok_or(Status::NotFound)?;
+	let entries: Vec<_> = tree
+		.iter()
+		.skip((page - 1) * TREE_PAGE)
+		.take(TREE_PAGE)
+		.map(|entry| json!({ "path": entry["path"], "type": entry["type"] }))
+		.collect();
+	let next_page = if page * TREE_PAGE < tree.len() {
+		(page + 1).to_string()
+	} else {
+		String::new()
+	};
+	Ok(GitLabPage {
+		entries: Json(entries),
+		next_page: Header::new("X-Next-Page", next_page),
+	})
+}
+
+#[rocket::get("/api/v4/projects/<project>/repository/commits?<page>&<path>")]
+fn gitlab_commits(
+	project: String,
+	page: usize,
+	path: Option<&str>,
+	_token: GitLabToken,
+) -> Result<GitLabPage, Status> {
+	let project = project.replace("%2F", "/");
+	let (owner, repo) = project.split_once('/').ok_or(Status::NotFound)?;
+	let commits = commits(owner, repo, path).ok_or(Status::NotFound)?;
+	let entries = if page == 1 {
+		commits
+			.into_iter()
+			.map(|date| json!({ "committed_date": date }))
+			.collect()
+	} else {
+		Vec::new()
+	};
+	Ok(GitLabPage {
+		entries: Json(entries),
+		next_page: Header::new("X-Next-Page", ""),
+	})
+}
+
+#[rocket::get("/api/v1/repos/<owner>/<repo>")]
+fn gitea_repo(owner: &str, repo: &str, auth: Authorization) -> Result<Json<Value>, Status> {
+	if auth.0 != format!("token {}", GITEA_TOKEN) {
+		return Err(Status::Unauthorized);
+	}
+	let private = visibility(owner, repo).ok_or(Status::NotFound)?;
+	Ok(Json(json!({
+		"full_name": format!("{}/{}", owner, repo),
+		"private": private,
+		"default_branch": "main",
+	})))
+}
+
+#[rocket::get("/api/v1/repos/<owner>/<repo>/git/trees/main?recursive=true&<page>")]
+fn gitea_tree(
+	owner: &str,
+	repo: &str,
+	page: usize,
+	auth: Authorization,
+) -> Result<Json<Value>, Status> {
+	if auth.0 != format!("token {}", GITEA_TOKEN) {
+		return Err(Status::Unauthorized);
+	}
+	let tree = tree(owner, repo).ok_or(Status::NotFound)?;
+	let entries: Vec<_> = tree
+		.iter()
+		.skip((page - 1) * TREE_PAGE)
+		.take(TREE_PAGE)
+		.collect();
+	Ok(Json(json!({
+		"sha": "main",
+		"tree": entries,
+		"truncated": page * TREE_PAGE < tree.len(),
+		"page": page,
+		"total_count": tree.len(),
+	})))
+}
+
+#[rocket::get("/api/v1/repos/<owner>/<repo>/commits?<page>&<path>")]
+fn gitea_commits(
+	owner: &str,
+	repo: &str,
+	page: usize,
+	path: Option<&str>,
+	auth: Authorization,
+) -> Result<Json<Value>, Status> {
+	if auth.0 != format!("token {}", GITEA_TOKEN) {
+		return Err(Status::Unauthorized);
+	}
+	let commits = commit_list(owner, repo, path).ok_or(Status::NotFound)?;
+	Ok(Json(if page == 1 { commits } else { json!([]) }))
+}
+
+pub fn server(port: u16) -> Rocket<Build> {
+	let figment = rocket::Config::figment()
+		.merge(("address", "127.0.0.1"))
+		.merge(("port", port))
+		.merge(("log_level", "off"));
+	rocket::custom(figment)
+		.mount("/gamma", rocket::routes![gamma_token, gamma_me, gamma_user])
+		.mount("/aoc", rocket::routes![aoc_leaderboard])
+		.mount("/github", rocket::routes![
+			github_repo,
+			github_tree,
+			github_commits
+		])
+		.mount("/gitlab", rocket::routes![
+			gitlab_project,
+			gitlab_tree,
+			gitlab_commits
+		])
+		.mount("/gitea", rocket::routes![
+			gitea_repo,
+			gitea_tree,
+			gitea_commits
+		])
+}
@@ -0,0 +1,214 @@
+mod common;
+
+use common::{create_year, delete_json, expire, get, get_json, json_body, mock, post_json};
+use rocket::{
+	http::Status,
+	serde::json::{json, Value},
+};
+
+fn setting<'v>(settings: &'v Option<Value>, key: &str) -> &'v Value {
+	settings
+		.as_ref()
+		.and_then(Value::as_array)
+		.and_then(|settings| settings.iter().find(|s| s["key"] == key))
+		.unwrap_or_else(|| panic!("Missing the {} setting", key))
+}
+
+#[rocket::async_test]
+async fn owners_edit_settings() {
+	let client = common::client().await;
+
+	let (status, settings) = get_json(&client, "/config.json", "owner-config").await;
+	assert_eq!(status, Status::Ok);
+	let cache_time = setting(&settings, "leaderboard-splits-cache-time");
+	assert_eq!(cache_time["value"], "60");
+	assert_eq!(cache_time["isOverridden"], false);
+	let github_id = setting(&settings, "github-client-id");
+	assert_eq!(github_id["value"], "id");
+	// Secrets are never sent back.
+	let session = setting(&settings, "aoc-session");
+	assert_eq!(session["isSet"], true);
+	assert!(session.get("value").is_none());
+	let github_token = setting(&settings, "github-token");
+	assert_eq!(github_token["isSet"], true);
+	assert!(github_token.get("value").is_none());
+
+	for (key, value) in [
+		("leaderboard-splits-cache-time", " 120 "),
+		("owner-groups", "digit, staff,"),
+	] {
+		let status = post_json(
+			&client,
+			"/config.json",
+			"owner-config",
+			json!({ "key": key, "value": value }),
+		)
+		.await;
+		assert_eq!(status, Status::Ok, "{}", key);
+	}
+	let (_, settings) = get_json(&client, "/config.json", "owner-config").await;
+	let cache_time = setting(&settings, "leaderboard-splits-cache-time");
+	assert_eq!(cache_time["value"], "120");
+	assert_eq!(cache_time["isOverridden"], true);
+	assert_eq!(setting(&settings, "owner-groups")["value"], "digit,staff");
+
+	// Saved settings outlive the server.
+	let client = common::client().await;
+	let (_, settings) = get_json(&client, "/config.json", "owner-config").await;
+	assert_eq!(
+		setting(&settings, "leaderboard-splits-cache-time")["value"],
+		"120"
+	);
+
+	for key in ["leaderboard-splits-cache-time", "owner-groups"] {
+		let status = delete_json(
+			&client,
+			"/config.json",
+			"owner-config",
+			json!({ "key": key }),
+		)
+		.await;
+		assert_eq!(status, Status::Ok, "{}", key);
+	}
+	let (_, settings) = get_json(&client, "/config.json", "owner-config").await;
+	let cache_time = setting(&settings, "leaderboard-splits-cache-time");
+	assert_eq!(cache_time["value"], "60");
+	assert_eq!(cache_time["isOverridden"], false);
+	assert_eq!(
+		setting(&settings, "owner-groups")["value"],
+		mock::OWNER_GROUP
+	);
+
+	let body = get(&client, "/config", "owner-config")
+		.await
+		.into_string()
+		.await
+		.unwrap();
+	assert!(body.contains("leaderboard-splits-cache-time"));
+}
+
+#[rocket::async_test]
+async fn settings_are_validated() {
+	let client = common::client().await;
+
+	for (key, value) in [
+		("leaderboard-cache-time", "soon"),
+		("profile-cache-time", "0"),
+		("aoc-session", " "),
+		("owner-groups", " , "),
+		("solution-patterns", "day, {day}"),
+		("solution-patterns", "{day}-{day}"),
+		("language-min-bytes", "-1"),
+		("languages-per-star", "maybe"),
+		("commit-window", "0"),
+		("commit-window", "9223372036854775807"),
+		("unknown", "1"),
+	] {
+		let status = post_json(
+			&client,
+			"/config.json",
+			"owner-config-invalid",
+			json!({ "key": key, "value": value }),
+		)
+		.await;
+		assert!(
+			status == Status::BadRequest || status == Status::UnprocessableEntity,
+			"{}: {}",
+			key,
+			status
+		);
+	}
+}
+
+#[rocket::async_test]
+async fn only_owners_see_settings() {
+	let client = common::client().await;
+
+	let (status, _) = get_json(&client, "/config.json", "config-user").await;
+	assert_eq!(status, Status::Forbidden);
+	let status = post_json(
+		&client,
+		"/config.json",
+		"config-user",
+		json!({ "key": "aoc-session", "value": "other-session" }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+	let response = get(&client, "/config", "config-user").await;
+	assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[rocket::async_test]
+async fn expired_aoc_sessions_serve_the_last_snapshot() {
+	let client = common::client().await;
+	create_year(&client, 2401, "2401", &["config-stale-a", "config-stale-b"]).await;
+	let response = client.get("/leaderboard/2401.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+
+	let status = post_json(
+		&client,
+		"/config.json",
+		"owner-config-stale",
+		json!({ "key": "aoc-session", "value": "expired-session" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	expire("leaderboard_2401");
+
+	let response = client.get("/leaderboard/2401.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let leaderboard: Option<Value> = json_body(response).await;
+	let cids: Vec<_> = leaderboard
+		.as_ref()
+		.and_then(Value::as_array)
+		.map(|entries| entries.iter().filter_map(|e| e["cid"].as_str()).collect())
+		.unwrap_or_default();
+	assert_eq!(cids, ["config-stale-a", "config-stale-b"]);
+	let body = client
+		.get("/leaderboard/2401")
+		.dispatch()
+		.await
+		.into_string()
+		.await
+		.unwrap();
+	assert!(body.contains("last snapshot"));
+	let body = get(&client, "/settings", "owner-config-stale")
+		.await
+		.into_string()
+		.await
+		.unwrap();
+	assert!(body.contains("The AoC session has expired"));
+
+	// Without a snapshot there is nothing to fall back to.
+	create_year(&client, 2402, "2402", &["config-stale-c"]).await;
+	let response = client.get("/leaderboard/2402.json").dispatch().await;
+	assert_eq!(response.status(), Status::ServiceUnavailable);
+
+	// Going back to the configured session.
+	let status = delete_json(
+		&client,
+		"/config.json",
+		"owner-config-stale",
+		json!({ "key": "aoc-session" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+	expire("leaderboard_2401");
+
+	let response = client.get("/leaderboard/2402.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let body = client
+		.get("/leaderboard/2401")
+		.dispatch()
+		.await
+		.into_string()
+		.await
+		.unwrap();
+	assert!(!body.contains("last snapshot"));
+	let body = get(&client, "/settings", "owner-config-stale")
+		.await
+		.into_string()
+		.await
+		.unwrap();
+	assert!(!body.contains("The AoC session has expired"));
+}
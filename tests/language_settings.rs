@@ -0,0 +1,94 @@
+mod common;
+
+use common::{create_year, expire, join, json_body, mock, post_json};
+use rocket::{
+	http::Status,
+	local::asynchronous::Client,
+	serde::json::{json, Value},
+};
+
+// Language settings apply to every year, so they are changed in a test binary
+// of their own where no other leaderboard depends on them.
+
+fn field(leaderboard: &Option<Value>, key: &str) -> Vec<Value> {
+	leaderboard
+		.as_ref()
+		.and_then(Value::as_array)
+		.map(|entries| entries.iter().map(|entry| entry[key].clone()).collect())
+		.unwrap_or_default()
+}
+
+async fn set_setting(client: &Client, key: &str, value: &str) {
+	let status = post_json(
+		client,
+		"/config.json",
+		"owner-languages",
+		json!({ "key": key, "value": value }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok, "{}", key);
+}
+
+// Skips the cache, for settings that changed.
+async fn languages(client: &Client, year: i32) -> Option<Value> {
+	expire(&format!("leaderboard_languages_{}", year));
+	let response = client
+		.get(format!("/leaderboard/{}/languages.json", year))
+		.dispatch()
+		.await;
+	json_body(response).await
+}
+
+#[rocket::async_test]
+async fn language_settings_change_the_languages_leaderboard() {
+	let client = common::client().await;
+	create_year(&client, 2312, "2312", &[]).await;
+	join(
+		&client,
+		2312,
+		"lang-set-a",
+		&mock::aoc_id("2312", 1),
+		Some("someone/rust"),
+	)
+	.await;
+	join(
+		&client,
+		2312,
+		"lang-set-b",
+		&mock::aoc_id("2312", 2),
+		Some("someone/polyglot"),
+	)
+	.await;
+
+	// Both stars of day 1 count once per language.
+	set_setting(&client, "languages-per-star", "true").await;
+	let leaderboard = languages(&client, 2312).await;
+	assert_eq!(field(&leaderboard, "cid"), [
+		json!("lang-set-b"),
+		json!("lang-set-a")
+	]);
+	assert_eq!(field(&leaderboard, "score"), [json!(4), json!(2)]);
+	assert_eq!(field(&leaderboard, "rank"), [json!(1), json!(2)]);
+
+	set_setting(&client, "language-min-bytes", "3000").await;
+	let leaderboard = languages(&client, 2312).await;
+	assert_eq!(field(&leaderboard, "languages"), [
+		json!(["Rust"]),
+		json!(["Rust"])
+	]);
+	assert_eq!(field(&leaderboard, "rank"), [json!(1), json!(1)]);
+
+	set_setting(&client, "language-min-bytes", "0").await;
+	set_setting(&client, "language-allowlist", "haskell, shell").await;
+	set_setting(&client, "language-denylist", "").await;
+	let leaderboard = languages(&client, 2312).await;
+	assert_eq!(field(&leaderboard, "cid"), [
+		json!("lang-set-b"),
+		json!("lang-set-a")
+	]);
+	assert_eq!(field(&leaderboard, "languages"), [
+		json!(["Haskell", "Shell"]),
+		json!([])
+	]);
+	assert_eq!(field(&leaderboard, "score"), [json!(4), json!(0)]);
+}
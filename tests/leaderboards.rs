@@ -0,0 +1,539 @@
+mod common;
+
+use common::{create_year, delete_json, expire, get, join, json_body, location, mock, post_json};
+use rocket::{
+	http::Status,
+	local::asynchronous::Client,
+	serde::json::{json, Value},
+};
+
+fn cids(leaderboard: &Option<Value>) -> Vec<&str> {
+	leaderboard
+		.as_ref()
+		.and_then(Value::as_array)
+		.map(|entries| entries.iter().filter_map(|e| e["cid"].as_str()).collect())
+		.unwrap_or_default()
+}
+
+#[rocket::async_test]
+async fn leaderboard_redirects_to_the_latest_year() {
+	let client = common::client().await;
+	create_year(&client, 2301, "2301", &[]).await;
+
+	let response = client.get("/leaderboard").dispatch().await;
+	assert_eq!(response.status(), Status::Found);
+	assert!(location(&response).unwrap().starts_with("/leaderboard/"));
+}
+
+#[rocket::async_test]
+async fn leaderboard_ranks_participants_by_score() {
+	let client = common::client().await;
+	create_year(&client, 2302, "2302", &[
+		"lb-score-a",
+		"lb-score-b",
+		"lb-score-c",
+	])
+	.await;
+	// Participants who aren't on the AoC leaderboard are left out.
+	join(&client, 2302, "lb-score-d", "2302999", None).await;
+
+	let response = client.get("/leaderboard/2302.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let leaderboard: Option<Value> = json_body(response).await;
+	assert_eq!(cids(&leaderboard), [
+		"lb-score-a",
+		"lb-score-b",
+		"lb-score-c"
+	]);
+	let first = &leaderboard.as_ref().unwrap()[0];
+	assert_eq!(first["score"], 30);
+	assert_eq!(first["rank"], 1);
+	assert_eq!(first["nick"], mock::nick("lb-score-a"));
+	assert_eq!(first["profileUnavailable"], false);
+
+	let response = get(&client, "/leaderboard/2302", "lb-score-a").await;
+	assert_eq!(response.status(), Status::Ok);
+	let body = response.into_string().await.unwrap();
+	assert!(body.contains(&mock::nick("lb-score-c")));
+	assert!(body.contains("2302-join2302"));
+
+	let response = client.get("/leaderboard/2302").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let body = response.into_string().await.unwrap();
+	assert!(!body.contains("2302-join2302"));
+}
+
+#[rocket::async_test]
+async fn leaderboards_of_a_year_are_merged() {
+	let client = common::client().await;
+	create_year(&client, 2701, "2701", &["lb-boards-a"]).await;
+	assert_eq!(
+		post_json(
+			&client,
+			"/years.json",
+			"owner",
+			json!({ "year": 2701, "leaderboard": "2702-join2701" }),
+		)
+		.await,
+		Status::Ok
+	);
+	join(&client, 2701, "lb-boards-b", &mock::aoc_id("2702", 1), None).await;
+
+	// Both leaderboards have three members, so the local scores are
+	// recomputed as if all six were on the same leaderboard.
+	let response = client.get("/leaderboard/2701.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let leaderboard: Option<Value> = json_body(response).await;
+	assert_eq!(cids(&leaderboard), ["lb-boards-a", "lb-boards-b"]);
+	assert_eq!(leaderboard.as_ref().unwrap()[0]["score"], 10);
+	assert_eq!(leaderboard.as_ref().unwrap()[1]["score"], 8);
+
+	// Participants are shown the code of the leaderboard they are on.
+	let response = get(&client, "/leaderboard/2701", "lb-boards-b").await;
+	let body = response.into_string().await.unwrap();
+	assert!(body.contains("2702-join2701"));
+	assert!(!body.contains("2701-join2701"));
+	let response = get(&client, "/leaderboard/2701", "lb-boards-c").await;
+	let body = response.into_string().await.unwrap();
+	assert!(body.contains("2701-join2701"));
+}
+
+#[rocket::async_test]
+async fn local_scores_can_count_participants_only() {
+	let client = common::client().await;
+	create_year(&client, 2801, "2801", &["lb-only-a"]).await;
+	join(&client, 2801, "lb-only-c", &mock::aoc_id("2801", 3), None).await;
+
+	let response = client.get("/leaderboard/2801.json").dispatch().await;
+	let leaderboard: Option<Value> = json_body(response).await;
+	assert_eq!(leaderboard.as_ref().unwrap()[0]["score"], 30);
+	assert_eq!(leaderboard.as_ref().unwrap()[1]["score"], 10);
+
+	assert_eq!(
+		post_json(
+			&client,
+			"/years/scoring.json",
+			"owner",
+			json!({ "year": 2801, "participantsOnly": true }),
+		)
+		.await,
+		Status::Ok
+	);
+	let response = client.get("/years.json").dispatch().await;
+	let years: Option<Value> = json_body(response).await;
+	let year = years
+		.as_ref()
+		.and_then(Value::as_array)
+		.and_then(|years| years.iter().find(|y| y["year"] == 2801))
+		.unwrap();
+	assert_eq!(year["scoring"]["participantsOnly"], true);
+
+	// Member 02 isn't registered, so only the other two are scored.
+	let response = client.get("/leaderboard/2801.json").dispatch().await;
+	let leaderboard: Option<Value> = json_body(response).await;
+	assert_eq!(cids(&leaderboard), ["lb-only-a", "lb-only-c"]);
+	assert_eq!(leaderboard.as_ref().unwrap()[0]["score"], 4);
+	assert_eq!(leaderboard.as_ref().unwrap()[1]["score"], 1);
+
+	let status = post_json(
+		&client,
+		"/years/scoring.json",
+		"lb-only-a",
+		json!({ "year": 2801, "participantsOnly": false }),
+	)
+	.await;
+	assert_eq!(status, Status::Forbidden);
+}
+
+#[rocket::async_test]
+async fn years_have_scoring_rules() {
+	let client = common::client().await;
+	create_year(&client, 2901, "2901", &[
+		"lb-rules-a",
+		"lb-rules-b",
+		"lb-rules-c",
+	])
+	.await;
+	let set_scoring = |scoring: Value| post_json(&client, "/years/scoring.json", "owner", scoring);
+	let scores = |leaderboard: &Option<Value>| -> Vec<(String, u64)> {
+		leaderboard
+			.as_ref()
+			.and_then(Value::as_array)
+			.unwrap()
+			.iter()
+			.map(|e| {
+				(
+					e["cid"].as_str().unwrap().to_owned(),
+					e["score"].as_u64().unwrap(),
+				)
+			})
+			.collect()
+	};
+
+	let ranks = |leaderboard: &Option<Value>| -> Vec<u64> {
+		leaderboard
+			.as_ref()
+			.and_then(Value::as_array)
+			.unwrap()
+			.iter()
+			.map(|e| e["rank"].as_u64().unwrap())
+			.collect()
+	};
+
+	// Without a tie-breaker the first two share a place.
+	let status = set_scoring(json!({ "year": 2901, "points": "stars" })).await;
+	assert_eq!(status, Status::Ok);
+	let response = client.get("/leaderboard/2901.json").dispatch().await;
+	let leaderboard = json_body(response).await;
+	assert_eq!(ranks(&leaderboard), [1, 1, 3]);
+	let response = client.get("/leaderboard/2901").dispatch().await;
+	assert!(response
+		.into_string()
+		.await
+		.unwrap()
+		.contains("<li value=\"1\""));
+
+	let status =
+		set_scoring(json!({ "year": 2901, "points": "stars", "tieBreaker": "last-star" })).await;
+	assert_eq!(status, Status::Ok);
+	let response = client.get("/leaderboard/2901.json").dispatch().await;
+	let leaderboard = json_body(response).await;
+	assert_eq!(scores(&leaderboard), [
+		("lb-rules-a".to_owned(), 2),
+		("lb-rules-b".to_owned(), 2),
+		("lb-rules-c".to_owned(), 1),
+	]);
+	assert_eq!(ranks(&leaderboard), [1, 2, 3]);
+
+	// Every star of the mock leaderboards is on day 1.
+	let status = set_scoring(json!({ "year": 2901, "excludedDays": [1] })).await;
+	assert_eq!(status, Status::Ok);
+	let response = client.get("/leaderboard/2901.json").dispatch().await;
+	assert!(scores(&json_body(response).await)
+		.iter()
+		.all(|(_, score)| *score == 0));
+
+	// And they were all gotten long before anyone joined.
+	let status = set_scoring(json!({ "year": 2901, "lateJoiners": false })).await;
+	assert_eq!(status, Status::Ok);
+	let response = client.get("/leaderboard/2901.json").dispatch().await;
+	assert!(scores(&json_body(response).await)
+		.iter()
+		.all(|(_, score)| *score == 0));
+	let response = client.get("/leaderboard/2901/splits.json").dispatch().await;
+	let splits = json_body(response).await;
+	assert!(scores(&splits).iter().all(|(_, score)| *score == 0));
+	assert_eq!(ranks(&splits), [1, 1, 1]);
+
+	let status = set_scoring(json!({ "year": 2901, "excludedDays": [26] })).await;
+	assert_eq!(status, Status::BadRequest);
+	let status = set_scoring(json!({ "year": 2999 })).await;
+	assert_eq!(status, Status::NotFound);
+}
+
+#[rocket::async_test]
+async fn leaderboard_keeps_participants_without_a_profile() {
+	let client = common::client().await;
+	create_year(&client, 2303, "2303", &["hidden-lb-profile"]).await;
+
+	let response = client.get("/leaderboard/2303.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let leaderboard: Option<Value> = json_body(response).await;
+	assert_eq!(cids(&leaderboard), ["hidden-lb-profile"]);
+	let entry = &leaderboard.as_ref().unwrap()[0];
+	assert_eq!(entry["nick"], "hidden-lb-profile");
+	assert_eq!(entry["profileUnavailable"], true);
+
+	let response = client.get("/leaderboard/2303").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn leaderboard_ranks_participants_by_split() {
+	let client = common::client().await;
+	create_year(&client, 2304, "2304", &[
+		"lb-splits-a",
+		"lb-splits-b",
+		"lb-splits-c",
+	])
+	.await;
+
+	let response = client.get("/leaderboard/2304/splits.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let leaderboard: Option<Value> = json_body(response).await;
+	assert_eq!(cids(&leaderboard), [
+		"lb-splits-a",
+		"lb-splits-b",
+		"lb-splits-c"
+	]);
+	let scores: Vec<_> = leaderboard
+		.unwrap()
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|e| e["score"].as_u64().unwrap())
+		.collect();
+	assert_eq!(scores, [3, 2, 0]);
+
+	let response = client.get("/leaderboard/2304/splits").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let body = response.into_string().await.unwrap();
+	assert!(body.contains(&mock::nick("lb-splits-b")));
+}
+
+#[rocket::async_test]
+async fn leaderboard_ranks_participants_by_languages() {
+	let client = common::client().await;
+	create_year(&client, 2305, "2305", &[]).await;
+	join(
+		&client,
+		2305,
+		"lb-lang-a",
+		&mock::aoc_id("2305", 1),
+		Some("someone/rust"),
+	)
+	.await;
+	join(
+		&client,
+		2305,
+		"lb-lang-b",
+		&mock::aoc_id("2305", 2),
+		Some("someone/polyglot"),
+	)
+	.await;
+	join(&client, 2305, "lb-lang-c", &mock::aoc_id("2305", 3), None).await;
+
+	let response = client
+		.get("/leaderboard/2305/languages.json")
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	let leaderboard: Option<Value> = json_body(response).await;
+	assert_eq!(cids(&leaderboard), ["lb-lang-b", "lb-lang-a"]);
+	// Only the languages of solved days count, Python is from day 2. Shell
+	// and Makefiles are denied by default.
+	assert_eq!(field(&leaderboard, "languages"), [
+		json!(["Haskell", "Rust"]),
+		json!(["Rust"])
+	]);
+	assert_eq!(field(&leaderboard, "score"), [json!(2), json!(1)]);
+
+	let response = client.get("/leaderboard/2305/languages").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let body = response.into_string().await.unwrap();
+	assert!(body.contains(&mock::nick("lb-lang-a")));
+}
+
+#[rocket::async_test]
+async fn languages_are_read_from_other_code_hosts() {
+	let client = common::client().await;
+	create_year(&client, 2309, "2309", &[]).await;
+	let gitlab = format!("{}/gitlab/someone/polyglot", common::mock_url());
+	let gitea = format!("{}/gitea/someone/rust", common::mock_url());
+	join(
+		&client,
+		2309,
+		"lb-host-a",
+		&mock::aoc_id("2309", 1),
+		Some(&gitlab),
+	)
+	.await;
+	join(
+		&client,
+		2309,
+		"lb-host-b",
+		&mock::aoc_id("2309", 2),
+		Some(&gitea),
+	)
+	.await;
+
+	let response = client
+		.get("/leaderboard/2309/languages.json")
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	let leaderboard: Option<Value> = json_body(response).await;
+	assert_eq!(cids(&leaderboard), ["lb-host-a", "lb-host-b"]);
+	assert_eq!(field(&leaderboard, "languages"), [
+		json!(["Haskell", "Rust"]),
+		json!(["Rust"])
+	]);
+	assert_eq!(field(&leaderboard, "github"), [json!(gitlab), json!(gitea)]);
+
+	let response = client.get("/leaderboard/2309/languages").dispatch().await;
+	let body = response.into_string().await.unwrap();
+	// Links are escaped like any other text.
+	assert!(body.contains("gitlab&#x2F;someone&#x2F;polyglot"));
+}
+
+#[rocket::async_test]
+async fn languages_are_kept_while_github_is_rate_limited() {
+	let client = common::client().await;
+	create_year(&client, 2307, "2307", &[]).await;
+	join(
+		&client,
+		2307,
+		"lb-limit-a",
+		&mock::aoc_id("2307", 1),
+		Some("someone/rust"),
+	)
+	.await;
+	// The second time the tree is unchanged.
+	for _ in 0..2 {
+		let leaderboard = languages(&client, 2307).await;
+		assert_eq!(field(&leaderboard, "languages"), [json!(["Rust"])]);
+	}
+
+	// The first tree of a throttled owner exhausts the rate limit, after
+	// which the trees GitHub sent before are used.
+	join(
+		&client,
+		2307,
+		"lb-limit-b",
+		&mock::aoc_id("2307", 2),
+		Some("throttled-2307/polyglot"),
+	)
+	.await;
+	for _ in 0..2 {
+		let leaderboard = languages(&client, 2307).await;
+		assert_eq!(cids(&leaderboard), ["lb-limit-b", "lb-limit-a"]);
+		assert_eq!(field(&leaderboard, "languages"), [
+			json!(["Haskell", "Rust"]),
+			json!(["Rust"])
+		]);
+	}
+
+	// Without any earlier trees, the languages of the last snapshot are kept.
+	let client = common::client().await;
+	let leaderboard = languages(&client, 2307).await;
+	assert_eq!(cids(&leaderboard), ["lb-limit-b", "lb-limit-a"]);
+	assert_eq!(field(&leaderboard, "languages"), [
+		json!(["Haskell", "Rust"]),
+		json!(["Rust"])
+	]);
+}
+
+// The mock repos are committed to during the puzzles of 2020.
+#[rocket::async_test]
+async fn leaderboard_ranks_participants_by_commits() {
+	let client = common::client().await;
+	create_year(&client, 2020, "2020", &[]).await;
+	let gitlab = format!("{}/gitlab/someone/polyglot", common::mock_url());
+	let gitea = format!("{}/gitea/someone/polyglot", common::mock_url());
+	for (member, cid, repo) in [
+		(1, "lb-commits-a", "someone/rust"),
+		(2, "lb-commits-b", gitlab.as_str()),
+		(3, "lb-commits-c", gitea.as_str()),
+	] {
+		join(
+			&client,
+			2020,
+			cid,
+			&mock::aoc_id("2020", member),
+			Some(repo),
+		)
+		.await;
+	}
+
+	let response = client
+		.get("/leaderboard/2020/commits.json")
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	let leaderboard: Option<Value> = json_body(response).await;
+	// Rust's solution of day 1 is committed to two days after it unlocked, the
+	// commit on time doesn't change it. A single star is enough for a day to
+	// count.
+	assert_eq!(cids(&leaderboard), [
+		"lb-commits-b",
+		"lb-commits-c",
+		"lb-commits-a"
+	]);
+	assert_eq!(field(&leaderboard, "days"), [
+		json!([1]),
+		json!([1]),
+		json!([])
+	]);
+	assert_eq!(field(&leaderboard, "rank"), [json!(1), json!(1), json!(3)]);
+
+	let response = client.get("/leaderboard/2020/commits").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+	let body = response.into_string().await.unwrap();
+	assert!(body.contains("within 24 hours"));
+	assert!(body.contains(&mock::nick("lb-commits-a")));
+
+	set_setting(&client, "commit-window", "72").await;
+	expire("leaderboard_commits_2020");
+	let response = client
+		.get("/leaderboard/2020/commits.json")
+		.dispatch()
+		.await;
+	let leaderboard = json_body(response).await;
+	assert_eq!(field(&leaderboard, "score"), [json!(1), json!(1), json!(1)]);
+	assert_eq!(field(&leaderboard, "rank"), [json!(1), json!(1), json!(1)]);
+
+	let status = delete_json(
+		&client,
+		"/config.json",
+		"owner-languages",
+		json!({ "key": "commit-window" }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok);
+}
+
+fn field(leaderboard: &Option<Value>, key: &str) -> Vec<Value> {
+	leaderboard
+		.as_ref()
+		.and_then(Value::as_array)
+		.map(|entries| entries.iter().map(|entry| entry[key].clone()).collect())
+		.unwrap_or_default()
+}
+
+async fn set_setting(client: &Client, key: &str, value: &str) {
+	let status = post_json(
+		client,
+		"/config.json",
+		"owner-languages",
+		json!({ "key": key, "value": value }),
+	)
+	.await;
+	assert_eq!(status, Status::Ok, "{}", key);
+}
+
+// Skips the cache, for settings that changed.
+async fn languages(client: &Client, year: i32) -> Option<Value> {
+	expire(&format!("leaderboard_languages_{}", year));
+	let response = client
+		.get(format!("/leaderboard/{}/languages.json", year))
+		.dispatch()
+		.await;
+	json_body(response).await
+}
+
+#[rocket::async_test]
+async fn unknown_years_are_not_found() {
+	let client = common::client().await;
+
+	for uri in [
+		"/leaderboard/2399.json",
+		"/leaderboard/2399",
+		"/leaderboard/2399/splits.json",
+		"/leaderboard/2399/splits",
+		"/leaderboard/twenty.json",
+	] {
+		let response = client.get(uri).dispatch().await;
+		assert_eq!(response.status(), Status::NotFound, "{}", uri);
+	}
+}
+
+#[rocket::async_test]
+async fn unavailable_aoc_leaderboards_are_errors() {
+	let client = common::client().await;
+	create_year(&client, 2306, "9306", &["lb-down-a"]).await;
+
+	for uri in ["/leaderboard/2306.json", "/leaderboard/2306/splits.json"] {
+		let response = client.get(uri).dispatch().await;
+		assert_eq!(response.status(), Status::ServiceUnavailable, "{}", uri);
+	}
+}
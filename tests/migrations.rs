@@ -0,0 +1,34 @@
+mod common;
+
+use common::create_year;
+use diesel::{dsl::sql, sql_types::BigInt, RunQueryDsl};
+use rocket::http::Status;
+
+fn applied_migrations() -> i64 {
+	sql::<BigInt>("SELECT COUNT(*) FROM __diesel_schema_migrations")
+		.get_result(&common::database())
+		.unwrap()
+}
+
+#[rocket::async_test]
+async fn migrations_are_reverted_and_rerun() {
+	let client = common::client().await;
+
+	digit_aoc::migrate(Some("list")).unwrap();
+	let applied = applied_migrations();
+	assert!(applied > 0);
+	for _ in 0..applied {
+		digit_aoc::migrate(Some("revert")).unwrap();
+	}
+	assert_eq!(applied_migrations(), 0);
+	let response = client.get("/years.json").dispatch().await;
+	assert_eq!(response.status(), Status::InternalServerError);
+
+	digit_aoc::migrate(Some("run")).unwrap();
+	assert_eq!(applied_migrations(), applied);
+	create_year(&client, 2501, "2501", &[]).await;
+	let response = client.get("/years.json").dispatch().await;
+	assert_eq!(response.status(), Status::Ok);
+
+	assert!(digit_aoc::migrate(Some("unknown")).is_err());
+}
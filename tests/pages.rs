@@ -0,0 +1,106 @@
+mod common;
+
+use common::{get, location, mock, SESSION_COOKIE};
+use rocket::http::Status;
+
+#[rocket::async_test]
+async fn index_and_about_render_for_everyone() {
+	let client = common::client().await;
+
+	for uri in ["/", "/about"] {
+		let response = client.get(uri).dispatch().await;
+		assert_eq!(response.status(), Status::Ok);
+		let body = response.into_string().await.unwrap();
+		assert!(body.contains("href=\"/login\""));
+
+		let response = get(&client, uri, "pages-index").await;
+		assert_eq!(response.status(), Status::Ok);
+		let body = response.into_string().await.unwrap();
+		assert!(body.contains(&mock::nick("pages-index")));
+		assert!(body.contains("href=\"/settings\""));
+	}
+}
+
+#[rocket::async_test]
+async fn login_redirects_to_the_identity_provider() {
+	let client = common::client().await;
+
+	let response = client.get("/login?back=/settings").dispatch().await;
+	assert_eq!(response.status(), Status::SeeOther);
+	let authorize = location(&response).unwrap();
+	assert!(authorize.contains("/gamma/api/oauth/authorize"));
+	assert!(authorize.contains("state=%2Fsettings"));
+
+	let response = get(&client, "/login?back=/settings", "pages-login").await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+}
+
+#[rocket::async_test]
+async fn local_login_is_only_available_with_the_local_provider() {
+	let client = common::client().await;
+
+	let response = client.get("/login/local?state=/").dispatch().await;
+	assert_eq!(response.status(), Status::NotFound);
+}
+
+#[rocket::async_test]
+async fn callback_signs_in() {
+	let client = common::client().await;
+
+	let response = client
+		.get("/callback?code=pages-callback&state=%2Fsettings")
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::SeeOther);
+	assert_eq!(location(&response), Some("/settings"));
+	assert_eq!(
+		response.cookies().get(SESSION_COOKIE).map(|c| c.value()),
+		Some(mock::token("pages-callback").as_str())
+	);
+
+	let response = client.get("/callback?code=ghost-callback").dispatch().await;
+	assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn callback_shows_provider_errors() {
+	let client = common::client().await;
+
+	let response = client
+		.get("/callback?error=access_denied&error_description=Nope")
+		.dispatch()
+		.await;
+	assert_eq!(response.status(), Status::Ok);
+	assert_eq!(
+		response.into_string().await.as_deref(),
+		Some("access_denied\nNope")
+	);
+}
+
+#[rocket::async_test]
+async fn settings_require_a_session() {
+	let client = common::client().await;
+
+	let response = client.get("/settings").dispatch().await;
+	assert_eq!(response.status(), Status::Unauthorized);
+
+	let response = get(&client, "/settings", "ghost-settings").await;
+	assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn settings_show_administration_to_owners_only() {
+	let client = common::client().await;
+
+	let response = get(&client, "/settings", "pages-settings").await;
+	assert_eq!(response.status(), Status::Ok);
+	let body = response.into_string().await.unwrap();
+	assert!(body.contains("action=\"/aoc-id\""));
+	assert!(!body.contains("action=\"/roles\""));
+
+	let response = get(&client, "/settings", "owner-settings").await;
+	assert_eq!(response.status(), Status::Ok);
+	let body = response.into_string().await.unwrap();
+	assert!(body.contains("action=\"/roles\""));
+}